@@ -1,5 +1,9 @@
 use std::collections::{HashSet, VecDeque};
-use std::io::Read;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use robot::{Coord, Direction, Pose, Protocol, Robot};
 
 #[derive(Debug)]
 enum ParamMode {
@@ -122,9 +126,6 @@ impl IoBuffer {
     fn write(&mut self, value: i128) {
         self.values.push_back(value)
     }
-    fn is_empty(&self) -> bool {
-        self.values.is_empty()
-    }
 }
 
 #[derive(Debug)]
@@ -423,7 +424,7 @@ impl IntcodeComputer {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 enum Colour {
     Black,
     White,
@@ -445,114 +446,406 @@ impl Colour {
     }
 }
 
-struct Grid {
-    cells: Vec<Colour>,
-    width: usize,
-    height: usize,
+// Unbounded in every direction. Only white panels are stored since the hull starts out black.
+#[derive(Default)]
+struct Hull {
+    white: HashSet<Coord>,
+    painted: HashSet<Coord>,
 }
 
-impl Grid {
-    fn new(width: usize, height: usize) -> Self {
-        let cells = vec![Colour::Black; width * height];
-        Self {
-            cells,
-            width,
-            height,
+impl Hull {
+    fn get(&self, coord: Coord) -> Colour {
+        if self.white.contains(&coord) {
+            Colour::White
+        } else {
+            Colour::Black
+        }
+    }
+    fn paint(&mut self, coord: Coord, colour: Colour) {
+        match colour {
+            Colour::White => self.white.insert(coord),
+            Colour::Black => self.white.remove(&coord),
+        };
+        self.painted.insert(coord);
+    }
+    fn num_painted(&self) -> usize {
+        self.painted.len()
+    }
+    // The smallest rectangle containing every white panel, as its top-left and bottom-right
+    fn bounding_box(&self) -> Option<(Coord, Coord)> {
+        let mut coords = self.white.iter();
+        let &first = coords.next()?;
+        Some(coords.fold((first, first), |(min, max), &coord| {
+            (
+                Coord {
+                    x: min.x.min(coord.x),
+                    y: min.y.min(coord.y),
+                },
+                Coord {
+                    x: max.x.max(coord.x),
+                    y: max.y.max(coord.y),
+                },
+            )
+        }))
+    }
+    fn cropped_rows(&self) -> Vec<Vec<Colour>> {
+        let (min, max) = match self.bounding_box() {
+            Some(bounding_box) => bounding_box,
+            None => return Vec::new(),
+        };
+        (min.y..=max.y)
+            .map(|y| (min.x..=max.x).map(|x| self.get(Coord { x, y })).collect())
+            .collect()
+    }
+    fn to_ascii(&self) -> String {
+        let mut string = String::new();
+        for row in self.cropped_rows() {
+            for colour in row {
+                string.push(match colour {
+                    Colour::White => '#',
+                    Colour::Black => '.',
+                });
+            }
+            string.push('\n');
         }
+        string
     }
-    fn get_mut(&mut self, Coord { x, y }: Coord) -> &mut Colour {
-        assert!(x >= 0);
-        assert!(y >= 0);
-        assert!((x as usize) < self.width);
-        assert!((y as usize) < self.height);
-        let index = self.width * y as usize + x as usize;
-        &mut self.cells[index]
+    fn scaled_pixels(&self, scale: usize) -> (usize, usize, Vec<Colour>) {
+        let rows = self.cropped_rows();
+        let width = rows.first().map_or(0, Vec::len) * scale;
+        let height = rows.len() * scale;
+        let mut pixels = Vec::with_capacity(width * height);
+        for row in &rows {
+            for _ in 0..scale {
+                for &colour in row {
+                    pixels.resize(pixels.len() + scale, colour);
+                }
+            }
+        }
+        (width, height, pixels)
+    }
+    fn to_pbm(&self, scale: usize) -> String {
+        let (width, height, pixels) = self.scaled_pixels(scale);
+        let mut string = format!("P1\n{} {}\n", width, height);
+        for row in pixels.chunks(width.max(1)) {
+            let bits = row
+                .iter()
+                .map(|&colour| if colour == Colour::Black { "1" } else { "0" })
+                .collect::<Vec<_>>();
+            string.push_str(&bits.join(" "));
+            string.push('\n');
+        }
+        string
+    }
+    fn to_ppm(&self, scale: usize) -> String {
+        let (width, height, pixels) = self.scaled_pixels(scale);
+        let mut string = format!("P3\n{} {}\n255\n", width, height);
+        for row in pixels.chunks(width.max(1)) {
+            let rgbs = row
+                .iter()
+                .map(|&colour| match colour {
+                    Colour::White => "255 255 255",
+                    Colour::Black => "0 0 0",
+                })
+                .collect::<Vec<_>>();
+            string.push_str(&rgbs.join(" "));
+            string.push('\n');
+        }
+        string
+    }
+    fn to_svg(&self, scale: usize) -> String {
+        let rows = self.cropped_rows();
+        let width = rows.first().map_or(0, Vec::len) * scale;
+        let height = rows.len() * scale;
+        let mut string = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">\n",
+            width, height
+        );
+        string.push_str(&format!(
+            "  <rect width=\"{}\" height=\"{}\" fill=\"black\"/>\n",
+            width, height
+        ));
+        for (y, row) in rows.iter().enumerate() {
+            for (x, &colour) in row.iter().enumerate() {
+                if colour == Colour::White {
+                    string.push_str(&format!(
+                        "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"white\"/>\n",
+                        x * scale,
+                        y * scale,
+                        scale,
+                        scale
+                    ));
+                }
+            }
+        }
+        string.push_str("</svg>\n");
+        string
+    }
+    fn export(&self, path: &Path, scale: usize) {
+        let contents = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("txt") => self.to_ascii(),
+            Some("pbm") => self.to_pbm(scale),
+            Some("ppm") => self.to_ppm(scale),
+            Some("svg") => self.to_svg(scale),
+            _ => panic!("unknown image format: {}", path.display()),
+        };
+        std::fs::write(path, contents).unwrap();
     }
 }
 
-#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
-struct Coord {
-    x: i32,
-    y: i32,
+#[derive(Clone, Copy, Debug)]
+enum Turn {
+    Left,
+    Right,
 }
 
-impl Coord {
-    fn left(self) -> Self {
-        Self {
-            x: self.y,
-            y: -self.x,
-        }
+#[derive(Clone, Copy, Debug)]
+struct PaintStep {
+    position: Coord,
+    facing: Direction,
+    colour_read: Colour,
+    colour_painted: Colour,
+    turn: Turn,
+}
+
+impl PaintStep {
+    // The robot's position and facing once it has turned and moved forward
+    fn robot_after(&self) -> Pose {
+        let pose = Pose {
+            position: self.position,
+            facing: self.facing,
+        };
+        Painter::advance(pose, &self.colour_read, &(self.colour_painted, self.turn))
     }
-    fn right(self) -> Self {
-        Self {
-            x: -self.y,
-            y: self.x,
-        }
+}
+
+impl std::fmt::Display for PaintStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "at ({}, {}) facing {}: read {:?}, painted {:?}, turned {:?}",
+            self.position.x,
+            self.position.y,
+            facing_glyph(self.facing),
+            self.colour_read,
+            self.colour_painted,
+            self.turn
+        )
     }
 }
 
-impl std::ops::Add for Coord {
-    type Output = Self;
-    fn add(self, rhs: Self) -> Self {
-        Self {
-            x: self.x + rhs.x,
-            y: self.y + rhs.y,
+fn facing_glyph(facing: Direction) -> char {
+    match facing {
+        Direction::North => '^',
+        Direction::East => '>',
+        Direction::South => 'v',
+        Direction::West => '<',
+    }
+}
+
+const START: Pose = Pose {
+    position: Coord { x: 0, y: 0 },
+    facing: Direction::North,
+};
+
+fn initial_hull() -> Hull {
+    let mut hull = Hull::default();
+    hull.white.insert(START.position);
+    hull
+}
+
+struct Computer {
+    computer: IntcodeComputer,
+    input_buffer: IoBuffer,
+    output_buffer: IoBuffer,
+}
+
+impl robot::Machine for Computer {
+    fn write_input(&mut self, value: i128) {
+        self.input_buffer.write(value);
+    }
+    fn read_output(&mut self) -> Option<i128> {
+        match self
+            .computer
+            .run(&mut self.input_buffer, &mut self.output_buffer)
+        {
+            StopStatus::WroteOutput => self.output_buffer.read(),
+            StopStatus::Halt => None,
+            StopStatus::WaitForInput => panic!("robot asked for input before replying"),
         }
     }
 }
 
-const WIDTH: usize = 200;
-const HEIGHT: usize = 200;
+// The camera's view of the panel underneath is sent, and the program replies with the colour to
+// paint it and which way to turn before moving forward
+struct Painter;
+
+impl Protocol for Painter {
+    type Command = Colour;
+    type Status = (Colour, Turn);
+    const STATUS_LEN: usize = 2;
+    fn encode(colour: &Colour) -> Vec<i128> {
+        vec![colour.to_i128()]
+    }
+    fn decode(outputs: &[i128]) -> (Colour, Turn) {
+        let turn = match outputs[1] {
+            0 => Turn::Left,
+            1 => Turn::Right,
+            _ => panic!("unexpected turn"),
+        };
+        (Colour::from_i128(outputs[0]), turn)
+    }
+    fn advance(pose: Pose, _: &Colour, &(_, turn): &(Colour, Turn)) -> Pose {
+        let facing = match turn {
+            Turn::Left => pose.facing.left(),
+            Turn::Right => pose.facing.right(),
+        };
+        Pose {
+            position: pose.position + facing.to_unit_coord(),
+            facing,
+        }
+    }
+}
 
-fn count_robot_steps(program: &[i128]) -> usize {
-    let mut computer = IntcodeComputer::new(&program);
-    let mut input_buffer = IoBuffer::default();
-    let mut output_buffer = IoBuffer::default();
-    let mut grid = Grid::new(WIDTH, HEIGHT);
-    let mut robot_coord = Coord {
-        x: WIDTH as i32 / 2,
-        y: HEIGHT as i32 / 2,
+fn paint_hull(program: &[i128]) -> (Hull, Vec<PaintStep>) {
+    let computer = Computer {
+        computer: IntcodeComputer::new(&program),
+        input_buffer: IoBuffer::default(),
+        output_buffer: IoBuffer::default(),
     };
-    *grid.get_mut(robot_coord) = Colour::White;
-    let mut robot_facing = Coord { x: 0, y: -1 };
-    let mut painted: HashSet<Coord> = HashSet::new();
+    let mut robot = Robot::<_, Painter>::new(computer, START);
+    let mut hull = initial_hull();
+    let mut steps = Vec::new();
     loop {
-        match computer.run(&mut input_buffer, &mut output_buffer) {
-            StopStatus::Halt => {
-                for i in 0..HEIGHT {
-                    for j in 0..WIDTH {
-                        let cell = grid.get_mut(Coord {
-                            x: j as i32,
-                            y: i as i32,
-                        });
-                        let ch = match cell {
-                            Colour::White => '#',
-                            Colour::Black => '.',
-                        };
-                        print!("{}", ch);
-                    }
-                    println!("");
+        let Pose { position, facing } = robot.pose();
+        let colour_read = hull.get(position);
+        let (colour_painted, turn) = match robot.send(colour_read) {
+            Some(status) => status,
+            None => return (hull, steps),
+        };
+        hull.paint(position, colour_painted);
+        steps.push(PaintStep {
+            position,
+            facing,
+            colour_read,
+            colour_painted,
+            turn,
+        });
+    }
+}
+
+enum FrameOutput {
+    Terminal(Duration),
+    Ppm(PathBuf),
+}
+
+// Every frame covers the same area: everywhere the robot went
+fn animation_bounds(steps: &[PaintStep]) -> (Coord, Coord) {
+    let mut min = START.position;
+    let mut max = START.position;
+    for step in steps {
+        for &coord in &[step.position, step.robot_after().position] {
+            min = Coord {
+                x: min.x.min(coord.x),
+                y: min.y.min(coord.y),
+            };
+            max = Coord {
+                x: max.x.max(coord.x),
+                y: max.y.max(coord.y),
+            };
+        }
+    }
+    (min, max)
+}
+
+fn ascii_frame(hull: &Hull, (min, max): (Coord, Coord), robot: Pose) -> String {
+    let mut string = String::new();
+    for y in min.y..=max.y {
+        for x in min.x..=max.x {
+            let coord = Coord { x, y };
+            string.push(if coord == robot.position {
+                facing_glyph(robot.facing)
+            } else {
+                match hull.get(coord) {
+                    Colour::White => '#',
+                    Colour::Black => '.',
                 }
-                return painted.len();
-            }
-            StopStatus::WaitForInput => {
-                assert!(output_buffer.is_empty());
-                let cell = grid.get_mut(robot_coord);
-                input_buffer.write(cell.to_i128());
-                let status = computer.run(&mut input_buffer, &mut output_buffer);
-                assert_eq!(status, StopStatus::WroteOutput);
-                *cell = Colour::from_i128(output_buffer.read().unwrap());
-                painted.insert(robot_coord);
-                let status = computer.run(&mut input_buffer, &mut output_buffer);
-                assert_eq!(status, StopStatus::WroteOutput);
-                robot_facing = match output_buffer.read().unwrap() {
-                    0 => robot_facing.left(),
-                    1 => robot_facing.right(),
-                    _ => panic!("unexpected turn"),
+            });
+        }
+        string.push_str("\x1b[K\n");
+    }
+    string
+}
+
+// The robot is drawn in red, with the edge it's facing in yellow
+fn ppm_frame(hull: &Hull, (min, max): (Coord, Coord), robot: Pose, scale: usize) -> String {
+    let width = (max.x - min.x + 1) as usize * scale;
+    let height = (max.y - min.y + 1) as usize * scale;
+    let mut string = format!("P3\n{} {}\n255\n", width, height);
+    for py in 0..height {
+        let mut rgbs = Vec::with_capacity(width);
+        for px in 0..width {
+            let coord = Coord {
+                x: min.x + (px / scale) as i32,
+                y: min.y + (py / scale) as i32,
+            };
+            let rgb = if coord == robot.position {
+                let (dx, dy) = (px % scale, py % scale);
+                let leading_edge = match robot.facing {
+                    Direction::North => dy == 0,
+                    Direction::East => dx == scale - 1,
+                    Direction::South => dy == scale - 1,
+                    Direction::West => dx == 0,
                 };
-                robot_coord = robot_coord + robot_facing;
+                if leading_edge && scale > 1 {
+                    "255 255 0"
+                } else {
+                    "255 0 0"
+                }
+            } else {
+                match hull.get(coord) {
+                    Colour::White => "255 255 255",
+                    Colour::Black => "0 0 0",
+                }
+            };
+            rgbs.push(rgb);
+        }
+        string.push_str(&rgbs.join(" "));
+        string.push('\n');
+    }
+    string
+}
+
+fn animate(steps: &[PaintStep], output: &FrameOutput, scale: usize) {
+    let bounds = animation_bounds(steps);
+    let mut hull = initial_hull();
+    let mut robot = START;
+    let mut next_frame_time = Instant::now();
+    if let FrameOutput::Ppm(dir) = output {
+        std::fs::create_dir_all(dir).unwrap();
+    } else {
+        print!("\x1b[2J");
+    }
+    for frame in 0..=steps.len() {
+        match output {
+            FrameOutput::Terminal(frame_duration) => {
+                let now = Instant::now();
+                if next_frame_time > now {
+                    std::thread::sleep(next_frame_time - now);
+                }
+                next_frame_time = Instant::now() + *frame_duration;
+                print!("\x1b[H{}", ascii_frame(&hull, bounds, robot));
+                println!("step {} of {}\x1b[K", frame, steps.len());
+                std::io::stdout().flush().unwrap();
             }
-            StopStatus::WroteOutput => panic!("unexpected write"),
+            FrameOutput::Ppm(dir) => {
+                let path = dir.join(format!("frame{:05}.ppm", frame));
+                std::fs::write(path, ppm_frame(&hull, bounds, robot, scale)).unwrap();
+            }
+        }
+        if let Some(step) = steps.get(frame) {
+            hull.paint(step.position, step.colour_painted);
+            robot = step.robot_after();
         }
     }
 }
@@ -567,6 +860,146 @@ fn main() {
         .split(",")
         .map(|s| s.trim().parse::<i128>().unwrap())
         .collect::<Vec<_>>();
-    let num_steps = count_robot_steps(&program);
-    println!("{}", num_steps);
+    let mut scale = 1;
+    let mut exports = Vec::new();
+    let mut log = false;
+    let mut frame_output = None;
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().expect("expected a value after the option");
+        match arg.as_str() {
+            "--scale" => scale = value().parse::<usize>().expect("expected a scale factor"),
+            "--export" => exports.push(value()),
+            "--log" => log = true,
+            "--animate" => {
+                let fps = value().parse::<f64>().expect("expected frames per second");
+                frame_output = Some(FrameOutput::Terminal(Duration::from_secs_f64(1.0 / fps)));
+            }
+            "--frames" => frame_output = Some(FrameOutput::Ppm(PathBuf::from(value()))),
+            other => panic!("unknown option: {}", other),
+        }
+    }
+    let (hull, steps) = paint_hull(&program);
+    if let Some(frame_output) = frame_output.as_ref() {
+        animate(&steps, frame_output, scale);
+    }
+    if log {
+        for (i, step) in steps.iter().enumerate() {
+            println!("step {}: {}", i, step);
+        }
+    }
+    for path in exports {
+        hull.export(Path::new(path), scale);
+    }
+    print!("{}", hull.to_ascii());
+    println!("{}", hull.num_painted());
+    let rows = hull
+        .cropped_rows()
+        .iter()
+        .map(|row| row.iter().map(|&colour| colour == Colour::White).collect())
+        .collect::<Vec<_>>();
+    match ocr::recognise(&rows) {
+        Ok(text) => println!("{}", text),
+        Err(error) => println!("{}", error),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn paints_the_registration_identifier() {
+        let program = include_str!("../input")
+            .trim()
+            .split(',')
+            .map(|s| s.parse().unwrap())
+            .collect::<Vec<_>>();
+        let (hull, steps) = paint_hull(&program);
+        assert_eq!(hull.num_painted(), 249);
+        assert_eq!(steps[0].position, START.position);
+        for pair in steps.windows(2) {
+            let Pose { position, facing } = pair[0].robot_after();
+            assert_eq!((pair[1].position, pair[1].facing), (position, facing));
+        }
+    }
+
+    #[test]
+    fn exports_the_cropped_hull() {
+        let mut hull = Hull::default();
+        for &(x, y) in &[(-1, -1), (1, 0), (0, 1), (2, 2)] {
+            hull.paint(Coord { x, y }, Colour::White);
+        }
+        // painted panels which end up black don't count towards the crop
+        hull.paint(Coord { x: 2, y: 2 }, Colour::Black);
+        assert_eq!(hull.num_painted(), 4);
+        assert_eq!(hull.to_ascii(), "#..\n..#\n.#.\n");
+
+        let pbm = hull.to_pbm(2);
+        let lines = pbm.lines().collect::<Vec<_>>();
+        assert_eq!(&lines[..2], &["P1", "6 6"]);
+        assert_eq!(lines.len(), 2 + 6);
+        assert_eq!(lines[2], "0 0 1 1 1 1");
+        assert_eq!(lines[4], "1 1 1 1 0 0");
+
+        let ppm = hull.to_ppm(2);
+        let lines = ppm.lines().collect::<Vec<_>>();
+        assert_eq!(&lines[..3], &["P3", "6 6", "255"]);
+        assert_eq!(lines.len(), 3 + 6);
+        assert!(lines[3..]
+            .iter()
+            .all(|line| line.split(' ').count() == 6 * 3));
+        assert!(lines[3].starts_with("255 255 255 255 255 255 0 0 0"));
+
+        let svg = hull.to_svg(2);
+        assert!(svg
+            .starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"6\" height=\"6\">\n"));
+        // the black background plus one square per white panel
+        assert_eq!(svg.matches("<rect").count(), 1 + 3);
+        assert!(svg.contains("<rect x=\"4\" y=\"2\" width=\"2\" height=\"2\" fill=\"white\"/>"));
+    }
+
+    #[test]
+    fn logs_and_draws_each_step() {
+        // driving clockwise round a square faces the robot every way in turn
+        let mut pose = START;
+        let mut steps = Vec::new();
+        for _ in 0..4 {
+            let step = PaintStep {
+                position: pose.position,
+                facing: pose.facing,
+                colour_read: Colour::Black,
+                colour_painted: Colour::White,
+                turn: Turn::Right,
+            };
+            pose = step.robot_after();
+            steps.push(step);
+        }
+        assert_eq!(pose, START);
+        assert_eq!(
+            steps.iter().map(PaintStep::to_string).collect::<Vec<_>>(),
+            vec![
+                "at (0, 0) facing ^: read Black, painted White, turned Right",
+                "at (1, 0) facing >: read Black, painted White, turned Right",
+                "at (1, 1) facing v: read Black, painted White, turned Right",
+                "at (0, 1) facing <: read Black, painted White, turned Right",
+            ]
+        );
+        let bounds = animation_bounds(&steps);
+        assert_eq!(bounds, (Coord { x: 0, y: 0 }, Coord { x: 1, y: 1 }));
+        let mut hull = Hull::default();
+        hull.paint(Coord { x: 0, y: 1 }, Colour::White);
+        let frames = steps
+            .iter()
+            .map(|step| {
+                let robot = Pose {
+                    position: step.position,
+                    facing: step.facing,
+                };
+                ascii_frame(&hull, bounds, robot).replace("\x1b[K", "")
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(frames, vec!["^.\n#.\n", ".>\n#.\n", "..\n#v\n", "..\n<.\n"]);
+    }
 }
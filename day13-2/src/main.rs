@@ -1,5 +1,8 @@
 use std::collections::VecDeque;
-use std::io::Read;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::process::Command;
+use std::time::{Duration, Instant};
 
 #[derive(Debug)]
 enum ParamMode {
@@ -110,7 +113,7 @@ impl Opcode {
     }
 }
 
-#[derive(Default, Debug)]
+#[derive(Clone, Default, Debug)]
 struct IoBuffer {
     values: VecDeque<i128>,
 }
@@ -140,7 +143,7 @@ enum Status {
     Halt,
 }
 
-#[derive(Default)]
+#[derive(Clone, Default)]
 struct State {
     ip: usize,
     relative_base: i128,
@@ -384,6 +387,7 @@ impl Instruction {
     }
 }
 
+#[derive(Clone)]
 struct IntcodeComputer {
     memory: Vec<i128>,
     state: State,
@@ -454,56 +458,112 @@ impl Tile {
     }
 }
 
+// Covers exactly the tiles drawn so far, growing in any direction as the program draws outside it
+#[derive(Clone, Default)]
 struct Grid {
     cells: Vec<Tile>,
+    top_left: Coord,
     width: usize,
     height: usize,
+    ball: Option<Coord>,
+    paddle: Option<Coord>,
+    num_blocks: usize,
 }
 
 impl Grid {
-    fn new(width: usize, height: usize) -> Self {
-        let cells = vec![Tile::Empty; width * height];
-        Self {
-            cells,
-            width,
-            height,
+    fn index(&self, Coord { x, y }: Coord) -> Option<usize> {
+        let x = x - self.top_left.x;
+        let y = y - self.top_left.y;
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return None;
         }
+        Some(self.width * y as usize + x as usize)
     }
-    fn get_mut(&mut self, Coord { x, y }: Coord) -> &mut Tile {
-        assert!(x >= 0);
-        assert!(y >= 0);
-        assert!((x as usize) < self.width);
-        assert!((y as usize) < self.height);
-        let index = self.width * y as usize + x as usize;
-        &mut self.cells[index]
+    fn get(&self, coord: Coord) -> Option<Tile> {
+        self.index(coord).map(|index| self.cells[index])
     }
-    fn count_blocks(&self) -> usize {
-        self.cells.iter().filter(|&&c| c == Tile::Block).count()
-    }
-    fn tile_coord(&self, query_tile: Tile) -> Coord {
-        for (i, row) in self.cells.chunks_exact(self.width).enumerate() {
-            for (j, &tile) in row.iter().enumerate() {
-                if tile == query_tile {
-                    return Coord {
-                        x: j as i32,
-                        y: i as i32,
-                    };
-                }
+    fn grow_to_fit(&mut self, coord: Coord) {
+        if self.index(coord).is_some() {
+            return;
+        }
+        let (top_left, bottom_right) = if self.cells.is_empty() {
+            (coord, coord)
+        } else {
+            let bottom_right = Coord {
+                x: self.top_left.x + self.width as i32 - 1,
+                y: self.top_left.y + self.height as i32 - 1,
+            };
+            (
+                Coord {
+                    x: self.top_left.x.min(coord.x),
+                    y: self.top_left.y.min(coord.y),
+                },
+                Coord {
+                    x: bottom_right.x.max(coord.x),
+                    y: bottom_right.y.max(coord.y),
+                },
+            )
+        };
+        let width = (bottom_right.x - top_left.x + 1) as usize;
+        let height = (bottom_right.y - top_left.y + 1) as usize;
+        let mut cells = vec![Tile::Empty; width * height];
+        if self.width > 0 {
+            for (i, row) in self.cells.chunks_exact(self.width).enumerate() {
+                let x = (self.top_left.x - top_left.x) as usize;
+                let y = (self.top_left.y - top_left.y) as usize + i;
+                let start = y * width + x;
+                cells[start..start + self.width].copy_from_slice(row);
             }
         }
-        panic!("no ball");
+        self.cells = cells;
+        self.top_left = top_left;
+        self.width = width;
+        self.height = height;
+    }
+    fn set(&mut self, coord: Coord, tile: Tile) {
+        self.grow_to_fit(coord);
+        let index = self.index(coord).unwrap();
+        let previous = std::mem::replace(&mut self.cells[index], tile);
+        match previous {
+            Tile::Block => self.num_blocks -= 1,
+            Tile::Ball if self.ball == Some(coord) => self.ball = None,
+            Tile::HorizontalPaddle if self.paddle == Some(coord) => self.paddle = None,
+            _ => (),
+        }
+        match tile {
+            Tile::Block => self.num_blocks += 1,
+            Tile::Ball => self.ball = Some(coord),
+            Tile::HorizontalPaddle => self.paddle = Some(coord),
+            _ => (),
+        }
+    }
+    fn count_blocks(&self) -> usize {
+        self.num_blocks
+    }
+    fn rows(&self) -> impl Iterator<Item = &[Tile]> {
+        // chunks_exact panics on a zero chunk size
+        self.cells.chunks_exact(self.width.max(1))
     }
     fn render(&self) {
-        for row in self.cells.chunks_exact(self.width) {
+        for row in self.rows() {
             for tile in row {
                 print!("{}", tile.to_char());
             }
             println!("");
         }
     }
+    // Ends each line with a clear-to-end escape so it can be drawn over the previous frame
+    fn to_frame_string(&self) -> String {
+        let mut string = String::new();
+        for row in self.rows() {
+            string.extend(row.iter().map(|tile| tile.to_char()));
+            string.push_str("\x1b[K\n");
+        }
+        string
+    }
 }
 
-#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, Default, Hash, PartialEq, Eq, Debug)]
 struct Coord {
     x: i32,
     y: i32,
@@ -519,46 +579,373 @@ impl std::ops::Add for Coord {
     }
 }
 
-const WIDTH: usize = 100;
-const HEIGHT: usize = 40;
-
-fn run(program: &[i128]) -> i128 {
-    let mut computer = IntcodeComputer::new(&program);
-    computer.memory[0] = 2;
-    let mut input_buffer = IoBuffer::default();
-    let mut output_buffer = IoBuffer::default();
-    let mut grid = Grid::new(WIDTH, HEIGHT);
-    let mut score = 0;
-    loop {
-        match computer.run(&mut input_buffer, &mut output_buffer) {
-            StopStatus::Halt => return score,
-            StopStatus::WaitForInput => {
-                grid.render();
-                let ball_coord = grid.tile_coord(Tile::Ball);
-                let paddle_coord = grid.tile_coord(Tile::HorizontalPaddle);
-                use std::cmp::Ordering;
-                let input = match ball_coord.x.cmp(&paddle_coord.x) {
-                    Ordering::Equal => 0,
-                    Ordering::Less => -1,
-                    Ordering::Greater => 1,
-                };
-                input_buffer.write(input);
+#[derive(Clone)]
+struct Arcade {
+    computer: IntcodeComputer,
+    input_buffer: IoBuffer,
+    output_buffer: IoBuffer,
+    grid: Grid,
+    score: i128,
+    print_score: bool,
+}
+
+impl Arcade {
+    fn new(program: &[i128]) -> Self {
+        let mut computer = IntcodeComputer::new(&program);
+        computer.memory[0] = 2;
+        Self {
+            computer,
+            input_buffer: IoBuffer::default(),
+            output_buffer: IoBuffer::default(),
+            grid: Grid::default(),
+            score: 0,
+            print_score: false,
+        }
+    }
+    // Runs the game until it asks for the joystick position, returning false if it halted instead
+    fn run_until_input(&mut self) -> bool {
+        loop {
+            match self
+                .computer
+                .run(&mut self.input_buffer, &mut self.output_buffer)
+            {
+                StopStatus::Halt => return false,
+                StopStatus::WaitForInput => return true,
+                StopStatus::WroteOutput => {
+                    if self.output_buffer.len() == 3 {
+                        let x = self.output_buffer.read().unwrap() as i32;
+                        let y = self.output_buffer.read().unwrap() as i32;
+                        let coord = Coord { x, y };
+                        if coord == (Coord { x: -1, y: 0 }) {
+                            self.score = self.output_buffer.read().unwrap();
+                            if self.print_score {
+                                println!("score: {}", self.score);
+                            }
+                        } else {
+                            let tile = Tile::from_i128(self.output_buffer.read().unwrap());
+                            self.grid.set(coord, tile);
+                        }
+                    }
+                }
             }
-            StopStatus::WroteOutput => {
-                if output_buffer.len() == 3 {
-                    let x = output_buffer.read().unwrap() as i32;
-                    let y = output_buffer.read().unwrap() as i32;
-                    let coord = Coord { x, y };
-                    if coord == (Coord { x: -1, y: 0 }) {
-                        score = output_buffer.read().unwrap();
-                        println!("score: {}", score);
-                    } else {
-                        let tile = Tile::from_i128(output_buffer.read().unwrap());
-                        *grid.get_mut(coord) = tile;
+        }
+    }
+    fn ball_and_paddle(&self) -> Option<(Coord, Coord)> {
+        Some((self.grid.ball?, self.grid.paddle?))
+    }
+}
+
+fn joystick_towards(paddle: Coord, target_x: i32) -> i128 {
+    use std::cmp::Ordering;
+    match target_x.cmp(&paddle.x) {
+        Ordering::Equal => 0,
+        Ordering::Less => -1,
+        Ordering::Greater => 1,
+    }
+}
+
+trait Strategy {
+    fn name(&self) -> &'static str;
+    fn joystick(&mut self, arcade: &Arcade) -> i128;
+}
+
+struct FollowBall;
+
+impl Strategy for FollowBall {
+    fn name(&self) -> &'static str {
+        "follow"
+    }
+    fn joystick(&mut self, arcade: &Arcade) -> i128 {
+        match arcade.ball_and_paddle() {
+            Some((ball, paddle)) => joystick_towards(paddle, ball.x),
+            None => 0,
+        }
+    }
+}
+
+// Extrapolates the ball's last movement down to the paddle, bouncing off walls but not blocks
+#[derive(Default)]
+struct PredictLanding {
+    previous_ball: Option<Coord>,
+}
+
+impl Strategy for PredictLanding {
+    fn name(&self) -> &'static str {
+        "predict"
+    }
+    fn joystick(&mut self, arcade: &Arcade) -> i128 {
+        let (ball, paddle) = match arcade.ball_and_paddle() {
+            Some(ball_and_paddle) => ball_and_paddle,
+            None => return 0,
+        };
+        let previous_ball = self.previous_ball.replace(ball);
+        let target_x = match previous_ball {
+            Some(previous_ball) if ball.y > previous_ball.y => {
+                let mut dx = ball.x - previous_ball.x;
+                let mut x = ball.x;
+                for y in ball.y..(paddle.y - 1) {
+                    if arcade.grid.get(Coord {
+                        x: x + dx,
+                        y: y + 1,
+                    }) == Some(Tile::Wall)
+                    {
+                        dx = -dx;
                     }
+                    x += dx;
                 }
+                x
             }
+            _ => ball.x,
+        };
+        joystick_towards(paddle, target_x)
+    }
+}
+
+// Runs a clone of the machine with the joystick held still to find exactly where the ball will
+// reach the paddle's row. The ball's path on the way down doesn't depend on the paddle, so the
+// answer stays valid until the ball gets there.
+#[derive(Default)]
+struct SimulateAhead {
+    target_x: Option<i32>,
+}
+
+impl SimulateAhead {
+    fn landing_x(arcade: &Arcade) -> Option<i32> {
+        let (_, paddle) = arcade.ball_and_paddle()?;
+        let mut arcade = arcade.clone();
+        arcade.print_score = false;
+        loop {
+            let ball = arcade.grid.ball?;
+            if ball.y >= paddle.y - 1 {
+                return Some(ball.x);
+            }
+            arcade.input_buffer.write(0);
+            if !arcade.run_until_input() {
+                return None;
+            }
+        }
+    }
+}
+
+impl Strategy for SimulateAhead {
+    fn name(&self) -> &'static str {
+        "simulate"
+    }
+    fn joystick(&mut self, arcade: &Arcade) -> i128 {
+        let (ball, paddle) = match arcade.ball_and_paddle() {
+            Some(ball_and_paddle) => ball_and_paddle,
+            None => return 0,
+        };
+        if ball.y >= paddle.y - 1 {
+            self.target_x = None;
+            return joystick_towards(paddle, ball.x);
+        }
+        if self.target_x.is_none() {
+            self.target_x = Self::landing_x(arcade);
+        }
+        joystick_towards(paddle, self.target_x.unwrap_or(ball.x))
+    }
+}
+
+#[derive(Default, Debug)]
+struct Metrics {
+    frames: usize,
+    paddle_moves: usize,
+    blocks_remaining: Vec<usize>,
+}
+
+fn play(program: &[i128], strategy: &mut dyn Strategy, render: bool) -> (i128, Metrics) {
+    let mut arcade = Arcade::new(program);
+    arcade.print_score = render;
+    let mut metrics = Metrics::default();
+    while arcade.run_until_input() {
+        if render {
+            arcade.grid.render();
+        }
+        let input = strategy.joystick(&arcade);
+        metrics.frames += 1;
+        if input != 0 {
+            metrics.paddle_moves += 1;
         }
+        metrics.blocks_remaining.push(arcade.grid.count_blocks());
+        arcade.input_buffer.write(input);
+    }
+    // the final entry is the board the game ended on
+    metrics.blocks_remaining.push(arcade.grid.count_blocks());
+    (arcade.score, metrics)
+}
+
+fn all_strategies() -> Vec<Box<dyn Strategy>> {
+    vec![
+        Box::new(FollowBall),
+        Box::new(PredictLanding::default()),
+        Box::new(SimulateAhead::default()),
+    ]
+}
+
+fn print_metrics(name: &str, score: i128, metrics: &Metrics) {
+    println!(
+        "{}: score {}, {} frames, {} paddle moves, {} blocks remaining",
+        name,
+        score,
+        metrics.frames,
+        metrics.paddle_moves,
+        metrics.blocks_remaining.last().cloned().unwrap_or(0),
+    );
+}
+
+fn stty(tty: &File, args: &[&str]) -> Option<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(tty.try_clone().ok()?)
+        .output()
+        .ok()?;
+    if output.status.success() {
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        None
+    }
+}
+
+struct RawMode {
+    tty: File,
+    saved: String,
+}
+
+impl RawMode {
+    // Reads return immediately with whatever has been typed so far, so the game never waits on
+    // the keyboard. Ctrl-C arrives as a byte rather than a signal so the terminal gets restored.
+    fn enable(tty: &File) -> Option<Self> {
+        let saved = stty(tty, &["-g"])?;
+        stty(tty, &["-icanon", "-echo", "-isig", "min", "0", "time", "0"])?;
+        Some(Self {
+            tty: tty.try_clone().ok()?,
+            saved,
+        })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        stty(&self.tty, &[&self.saved]);
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Control {
+    Joystick(i128),
+    ToggleAutoPlay,
+    Quit,
+}
+
+fn read_controls(tty: &mut File) -> Vec<Control> {
+    let mut buffer = [0; 64];
+    let num_bytes = tty.read(&mut buffer).unwrap_or(0);
+    decode_controls(&buffer[..num_bytes])
+}
+
+// Arrow keys arrive as escape sequences; anything unrecognised is skipped
+fn decode_controls(bytes: &[u8]) -> Vec<Control> {
+    let mut bytes = bytes.iter().cloned();
+    let mut controls = Vec::new();
+    while let Some(byte) = bytes.next() {
+        controls.push(match byte {
+            27 => match (bytes.next(), bytes.next()) {
+                (Some(b'['), Some(b'D')) => Control::Joystick(-1),
+                (Some(b'['), Some(b'C')) => Control::Joystick(1),
+                (Some(b'['), Some(b'B')) => Control::Joystick(0),
+                _ => continue,
+            },
+            b'a' | b'h' => Control::Joystick(-1),
+            b'd' | b'l' => Control::Joystick(1),
+            b's' | b'j' | b' ' => Control::Joystick(0),
+            b'p' => Control::ToggleAutoPlay,
+            b'q' | 3 | 4 => Control::Quit,
+            _ => continue,
+        });
+    }
+    controls
+}
+
+fn draw(arcade: &Arcade, auto_play: bool) {
+    let mut screen = String::from("\x1b[H");
+    screen.push_str(&arcade.grid.to_frame_string());
+    screen.push_str(&format!(
+        "score: {:<8} blocks: {:<5} {}\x1b[K\n",
+        arcade.score,
+        arcade.grid.count_blocks(),
+        if auto_play { "[auto-play]" } else { "" },
+    ));
+    screen.push_str("left/right or a/d: move, s: hold still, p: auto-play, q: quit\x1b[K\n");
+    print!("{}", screen);
+    std::io::stdout().flush().unwrap();
+}
+
+fn play_interactive(program: &[i128], args: &[String]) {
+    let mut frame_duration = Duration::from_millis(1000 / 15);
+    let mut auto_play = false;
+    let mut strategy: Box<dyn Strategy> = Box::new(FollowBall);
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--auto" => auto_play = true,
+            "--fps" => {
+                let fps = args
+                    .next()
+                    .and_then(|fps| fps.parse::<f64>().ok())
+                    .expect("expected a number of frames per second");
+                frame_duration = Duration::from_secs_f64(1.0 / fps);
+            }
+            "--strategy" => {
+                let name = args.next().expect("expected a strategy name");
+                strategy = all_strategies()
+                    .into_iter()
+                    .find(|strategy| strategy.name() == name)
+                    .unwrap_or_else(|| panic!("unknown strategy: {}", name));
+            }
+            other => panic!("unknown option: {}", other),
+        }
+    }
+    let mut tty = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .expect("interactive mode needs a terminal");
+    let raw_mode = RawMode::enable(&tty).expect("failed to put the terminal into raw mode");
+    let mut arcade = Arcade::new(program);
+    let mut next_frame_time = Instant::now();
+    let mut quit = false;
+    print!("\x1b[2J");
+    while !quit && arcade.run_until_input() {
+        draw(&arcade, auto_play);
+        let now = Instant::now();
+        if next_frame_time > now {
+            std::thread::sleep(next_frame_time - now);
+        }
+        next_frame_time = Instant::now() + frame_duration;
+        let mut joystick = 0;
+        for control in read_controls(&mut tty) {
+            match control {
+                Control::Joystick(input) => joystick = input,
+                Control::ToggleAutoPlay => auto_play = !auto_play,
+                Control::Quit => quit = true,
+            }
+        }
+        // strategies keep track of the ball between frames, so consult them even when unused
+        let strategy_joystick = strategy.joystick(&arcade);
+        if auto_play {
+            joystick = strategy_joystick;
+        }
+        arcade.input_buffer.write(joystick);
+    }
+    draw(&arcade, auto_play);
+    drop(raw_mode);
+    if quit {
+        println!("quit with score {}", arcade.score);
+    } else if arcade.grid.count_blocks() == 0 {
+        println!("you win! final score {}", arcade.score);
+    } else {
+        println!("game over, final score {}", arcade.score);
     }
 }
 
@@ -572,6 +959,200 @@ fn main() {
         .split(",")
         .map(|s| s.trim().parse::<i128>().unwrap())
         .collect::<Vec<_>>();
-    let block_count = run(&program);
-    println!("{}", block_count);
+    let args = std::env::args().collect::<Vec<_>>();
+    match args.get(1).map(String::as_str) {
+        None => {
+            let (score, _) = play(&program, &mut FollowBall, true);
+            println!("{}", score);
+        }
+        Some("play") => play_interactive(&program, &args[2..]),
+        Some("compare") => {
+            for mut strategy in all_strategies() {
+                let (score, metrics) = play(&program, strategy.as_mut(), false);
+                print_metrics(strategy.name(), score, &metrics);
+            }
+        }
+        Some(name) => {
+            let mut strategy = all_strategies()
+                .into_iter()
+                .find(|strategy| strategy.name() == name)
+                .unwrap_or_else(|| panic!("unknown strategy: {}", name));
+            let (score, metrics) = play(&program, strategy.as_mut(), false);
+            for (frame, blocks) in metrics.blocks_remaining.iter().enumerate() {
+                println!("frame {}: {} blocks", frame, blocks);
+            }
+            print_metrics(strategy.name(), score, &metrics);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SCRATCH: i128 = 1000;
+
+    // A game that draws each frame's tiles and then waits for the joystick, ignoring it
+    fn program(frames: &[&[(i128, i128, i128)]]) -> Vec<i128> {
+        let mut program = vec![2, 0, 0, SCRATCH];
+        for frame in frames {
+            for &(x, y, tile) in frame.iter() {
+                program.extend_from_slice(&[104, x, 104, y, 104, tile]);
+            }
+            program.extend_from_slice(&[3, SCRATCH]);
+        }
+        program.push(99);
+        program
+    }
+
+    // The ball falls diagonally from (2, 1) to (5, 4), just above the paddle at (3, 5)
+    fn falling_ball() -> Vec<i128> {
+        program(&[
+            &[(3, 5, 3), (2, 1, 4), (0, 0, 2)],
+            &[(2, 1, 0), (3, 2, 4)],
+            &[(3, 2, 0), (4, 3, 4), (0, 0, 0)],
+            &[(4, 3, 0), (5, 4, 4), (-1, 0, 7)],
+        ])
+    }
+
+    fn first_frame(program: &[i128]) -> Arcade {
+        let mut arcade = Arcade::new(program);
+        assert!(arcade.run_until_input());
+        arcade
+    }
+
+    #[test]
+    fn follow_ball_moves_towards_the_ball() {
+        let arcade = first_frame(&falling_ball());
+        assert_eq!(FollowBall.joystick(&arcade), -1);
+    }
+
+    #[test]
+    fn predict_landing_bounces_off_walls() {
+        let mut arcade = Arcade::new(&[99]);
+        for y in 0..7 {
+            arcade.grid.set(Coord { x: 6, y }, Tile::Wall);
+        }
+        arcade
+            .grid
+            .set(Coord { x: 4, y: 6 }, Tile::HorizontalPaddle);
+        arcade.grid.set(Coord { x: 3, y: 1 }, Tile::Ball);
+        let mut strategy = PredictLanding::default();
+        // with nothing to extrapolate from it follows the ball
+        assert_eq!(strategy.joystick(&arcade), -1);
+        arcade.grid.set(Coord { x: 3, y: 1 }, Tile::Empty);
+        arcade.grid.set(Coord { x: 4, y: 2 }, Tile::Ball);
+        assert_eq!(FollowBall.joystick(&arcade), 0);
+        // down and right to (5, 3), off the wall at (6, 4) and back to land at x = 3
+        assert_eq!(strategy.joystick(&arcade), -1);
+    }
+
+    #[test]
+    fn simulate_ahead_heads_for_the_landing() {
+        let arcade = first_frame(&falling_ball());
+        assert_eq!(SimulateAhead::landing_x(&arcade), Some(5));
+        let mut strategy = SimulateAhead::default();
+        assert_eq!(strategy.joystick(&arcade), 1);
+        // the simulation runs on a clone
+        assert_eq!(arcade.grid.ball, Some(Coord { x: 2, y: 1 }));
+    }
+
+    #[test]
+    fn play_records_metrics() {
+        let (score, metrics) = play(&falling_ball(), &mut FollowBall, false);
+        assert_eq!(score, 7);
+        assert_eq!(metrics.frames, 4);
+        // left, still, then right twice
+        assert_eq!(metrics.paddle_moves, 3);
+        assert_eq!(metrics.blocks_remaining, [1, 1, 0, 0, 0]);
+    }
+
+    #[test]
+    fn grid_grows_in_every_direction() {
+        let mut grid = Grid::default();
+        assert_eq!(grid.get(Coord::default()), None);
+        grid.set(Coord { x: 2, y: 1 }, Tile::Wall);
+        assert_eq!(
+            (grid.top_left, grid.width, grid.height),
+            (Coord { x: 2, y: 1 }, 1, 1)
+        );
+        grid.set(Coord { x: -1, y: 3 }, Tile::Block);
+        assert_eq!(
+            (grid.top_left, grid.width, grid.height),
+            (Coord { x: -1, y: 1 }, 4, 3)
+        );
+        grid.set(Coord { x: 0, y: 0 }, Tile::Ball);
+        assert_eq!(
+            (grid.top_left, grid.width, grid.height),
+            (Coord { x: -1, y: 0 }, 4, 4)
+        );
+        // existing tiles keep their place
+        assert_eq!(grid.get(Coord { x: 2, y: 1 }), Some(Tile::Wall));
+        assert_eq!(grid.get(Coord { x: -1, y: 3 }), Some(Tile::Block));
+        assert_eq!(grid.get(Coord { x: 0, y: 3 }), Some(Tile::Empty));
+        assert_eq!(grid.get(Coord { x: 3, y: 1 }), None);
+        assert_eq!(
+            grid.to_frame_string(),
+            " o  \x1b[K\n   #\x1b[K\n    \x1b[K\n%   \x1b[K\n"
+        );
+    }
+
+    #[test]
+    fn grid_tracks_entities_incrementally() {
+        let mut grid = Grid::default();
+        let a = Coord { x: 1, y: 1 };
+        let b = Coord { x: 2, y: 2 };
+        grid.set(a, Tile::Block);
+        grid.set(b, Tile::Block);
+        grid.set(Coord { x: 0, y: 4 }, Tile::HorizontalPaddle);
+        assert_eq!(grid.count_blocks(), 2);
+        assert_eq!(grid.ball, None);
+        assert_eq!(grid.paddle, Some(Coord { x: 0, y: 4 }));
+        // the ball breaking a block replaces it
+        grid.set(a, Tile::Ball);
+        assert_eq!(grid.count_blocks(), 1);
+        assert_eq!(grid.ball, Some(a));
+        // the ball can be drawn at its new position before its old one is cleared
+        grid.set(b, Tile::Ball);
+        grid.set(a, Tile::Empty);
+        assert_eq!(grid.count_blocks(), 0);
+        assert_eq!(grid.ball, Some(b));
+        grid.set(b, Tile::Empty);
+        assert_eq!(grid.ball, None);
+        grid.set(Coord { x: 1, y: 4 }, Tile::HorizontalPaddle);
+        grid.set(Coord { x: 0, y: 4 }, Tile::Empty);
+        assert_eq!(grid.paddle, Some(Coord { x: 1, y: 4 }));
+    }
+
+    #[test]
+    fn decode_keys() {
+        assert_eq!(
+            decode_controls(b"\x1b[D\x1b[C\x1b[B"),
+            [
+                Control::Joystick(-1),
+                Control::Joystick(1),
+                Control::Joystick(0)
+            ]
+        );
+        assert_eq!(
+            decode_controls(b"ahdls jp"),
+            [
+                Control::Joystick(-1),
+                Control::Joystick(-1),
+                Control::Joystick(1),
+                Control::Joystick(1),
+                Control::Joystick(0),
+                Control::Joystick(0),
+                Control::Joystick(0),
+                Control::ToggleAutoPlay,
+            ]
+        );
+        assert_eq!(
+            decode_controls(b"q\x03\x04"),
+            [Control::Quit, Control::Quit, Control::Quit]
+        );
+        // an unknown escape sequence and stray keys are dropped
+        assert_eq!(decode_controls(b"\x1b[Axzd"), [Control::Joystick(1)]);
+        assert_eq!(decode_controls(b""), []);
+    }
 }
@@ -1,5 +1,8 @@
-use std::collections::{HashSet, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use robot::{Coord, Direction, GridMap, Navigate, Pose, Protocol, Robot, ALL_DIRECTIONS};
 
 #[derive(Debug)]
 enum ParamMode {
@@ -110,7 +113,7 @@ impl Opcode {
     }
 }
 
-#[derive(Default, Debug)]
+#[derive(Clone, Default, Debug)]
 struct IoBuffer {
     values: VecDeque<i128>,
 }
@@ -137,7 +140,7 @@ enum Status {
     Halt,
 }
 
-#[derive(Default)]
+#[derive(Clone, Default)]
 struct State {
     ip: usize,
     relative_base: i128,
@@ -381,6 +384,7 @@ impl Instruction {
     }
 }
 
+#[derive(Clone)]
 struct IntcodeComputer {
     memory: Vec<i128>,
     state: State,
@@ -420,61 +424,7 @@ impl IntcodeComputer {
     }
 }
 
-#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
-struct Coord {
-    x: i32,
-    y: i32,
-}
-
-impl std::ops::Add for Coord {
-    type Output = Self;
-    fn add(self, rhs: Self) -> Self {
-        Self {
-            x: self.x + rhs.x,
-            y: self.y + rhs.y,
-        }
-    }
-}
-
-#[derive(Clone, Copy, Debug)]
-#[repr(u8)]
-enum Direction {
-    North = 1,
-    South = 2,
-    West = 3,
-    East = 4,
-}
-
-const ALL_DIRECTIONS: [Direction; 4] = [
-    Direction::North,
-    Direction::South,
-    Direction::West,
-    Direction::East,
-];
-
-impl Direction {
-    fn to_i128(self) -> i128 {
-        self as i128
-    }
-    fn to_unit_coord(self) -> Coord {
-        match self {
-            Self::North => Coord { x: 0, y: -1 },
-            Self::South => Coord { x: 0, y: 1 },
-            Self::West => Coord { x: -1, y: 0 },
-            Self::East => Coord { x: 1, y: 0 },
-        }
-    }
-    fn opposite(self) -> Self {
-        match self {
-            Self::North => Self::South,
-            Self::South => Self::North,
-            Self::East => Self::West,
-            Self::West => Self::East,
-        }
-    }
-}
-
-#[derive(PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 #[repr(u8)]
 #[allow(dead_code)]
 enum DroidStatus {
@@ -490,46 +440,70 @@ impl DroidStatus {
     }
 }
 
-struct Node {
-    destination: Coord,
-    path_from_start: Vec<Direction>,
-}
-
-struct Droid {
+#[derive(Clone)]
+struct Computer {
     computer: IntcodeComputer,
     input_buffer: IoBuffer,
     output_buffer: IoBuffer,
 }
 
-impl Droid {
-    fn new(program: &[i128]) -> Self {
-        Self {
-            computer: IntcodeComputer::new(program),
-            input_buffer: IoBuffer::default(),
-            output_buffer: IoBuffer::default(),
-        }
+impl robot::Machine for Computer {
+    fn write_input(&mut self, value: i128) {
+        self.input_buffer.write(value);
     }
-    fn run(&mut self) -> StopStatus {
-        self.computer
+    fn read_output(&mut self) -> Option<i128> {
+        match self
+            .computer
             .run(&mut self.input_buffer, &mut self.output_buffer)
+        {
+            StopStatus::WroteOutput => self.output_buffer.read(),
+            StopStatus::Halt => None,
+            StopStatus::WaitForInput => panic!("droid asked for input before replying"),
+        }
     }
-    fn step(&mut self, direction: Direction) -> DroidStatus {
-        self.input_buffer.write(direction.to_i128());
-        let stop_status = self.run();
-        assert_eq!(stop_status, StopStatus::WroteOutput);
-        DroidStatus::from_i128(self.output_buffer.read().unwrap())
+}
+
+struct Droid;
+
+impl Protocol for Droid {
+    type Command = Direction;
+    type Status = DroidStatus;
+    const STATUS_LEN: usize = 1;
+    fn encode(&direction: &Direction) -> Vec<i128> {
+        vec![match direction {
+            Direction::North => 1,
+            Direction::South => 2,
+            Direction::West => 3,
+            Direction::East => 4,
+        }]
     }
-    fn follow_directions<I: IntoIterator<Item = Direction>>(&mut self, directions: I) {
-        for direction in directions {
-            let droid_status = self.step(direction);
-            assert_ne!(droid_status, DroidStatus::HitWall);
+    fn decode(outputs: &[i128]) -> DroidStatus {
+        DroidStatus::from_i128(outputs[0])
+    }
+    fn advance(pose: Pose, &direction: &Direction, status: &DroidStatus) -> Pose {
+        match status {
+            DroidStatus::HitWall => pose,
+            DroidStatus::Moved | DroidStatus::MovedToGoal => Pose {
+                position: pose.position + direction.to_unit_coord(),
+                facing: direction,
+            },
         }
     }
 }
 
+impl Navigate for Droid {
+    fn is_blocked(status: &DroidStatus) -> bool {
+        *status == DroidStatus::HitWall
+    }
+}
+
+const ORIGIN: Coord = Coord { x: 0, y: 0 };
+
+#[derive(PartialEq, Eq, Debug)]
 struct Map {
     walls: HashSet<Coord>,
     source: Coord,
+    start: Coord,
 }
 
 struct MapNode {
@@ -538,6 +512,25 @@ struct MapNode {
 }
 
 impl Map {
+    fn from_grid(grid: &GridMap<Droid>) -> Self {
+        let walls = grid
+            .cells
+            .iter()
+            .filter(|(_, status)| Droid::is_blocked(status))
+            .map(|(&coord, _)| coord)
+            .collect();
+        let source = grid
+            .cells
+            .iter()
+            .find(|(_, &status)| status == DroidStatus::MovedToGoal)
+            .map(|(&coord, _)| coord)
+            .expect("never found the oxygen system");
+        Self {
+            walls,
+            source,
+            start: grid.start,
+        }
+    }
     fn longest_path_length(&self) -> usize {
         let mut seen_set = HashSet::new();
         let mut queue = VecDeque::new();
@@ -567,63 +560,217 @@ impl Map {
     }
 }
 
-fn build_map(program: &[i128]) -> Map {
-    let mut droid = Droid::new(program);
-    let mut seen_set = HashSet::new();
-    let mut queue = VecDeque::new();
-    let mut walls = HashSet::new();
-    let mut source = None;
-    seen_set.insert(Coord { x: 0, y: 0 });
-    queue.push_back(Node {
-        destination: Coord { x: 0, y: 0 },
-        path_from_start: Vec::new(),
-    });
-    while let Some(Node {
-        destination,
-        path_from_start,
-    }) = queue.pop_front()
-    {
-        droid.follow_directions(path_from_start.iter().cloned());
-        for &direction in &ALL_DIRECTIONS {
-            let next_destination = destination + direction.to_unit_coord();
-            if !seen_set.contains(&next_destination) {
-                seen_set.insert(next_destination);
-                let status = droid.step(direction);
-                match status {
-                    DroidStatus::HitWall => {
-                        walls.insert(next_destination);
+impl Map {
+    fn bounds(&self) -> (Coord, Coord) {
+        let mut min = self.start;
+        let mut max = self.start;
+        for wall in &self.walls {
+            min = Coord {
+                x: min.x.min(wall.x),
+                y: min.y.min(wall.y),
+            };
+            max = Coord {
+                x: max.x.max(wall.x),
+                y: max.y.max(wall.y),
+            };
+        }
+        (min, max)
+    }
+    fn distances_from(&self, from: Coord) -> HashMap<Coord, usize> {
+        let mut distances = HashMap::new();
+        let mut queue = VecDeque::new();
+        distances.insert(from, 0);
+        queue.push_back(from);
+        while let Some(coord) = queue.pop_front() {
+            let next_distance = distances[&coord] + 1;
+            for &direction in &ALL_DIRECTIONS {
+                let next_coord = coord + direction.to_unit_coord();
+                if !self.walls.contains(&next_coord) && !distances.contains_key(&next_coord) {
+                    distances.insert(next_coord, next_distance);
+                    queue.push_back(next_coord);
+                }
+            }
+        }
+        distances
+    }
+    // Includes both ends
+    fn shortest_path(&self, from: Coord, to: Coord) -> Option<Vec<Coord>> {
+        let distances = self.distances_from(from);
+        let mut coord = to;
+        let mut path = vec![to];
+        let mut distance = *distances.get(&to)?;
+        while distance > 0 {
+            distance -= 1;
+            coord = ALL_DIRECTIONS
+                .iter()
+                .map(|direction| coord + direction.to_unit_coord())
+                .find(|neighbour| distances.get(neighbour) == Some(&distance))
+                .unwrap();
+            path.push(coord);
+        }
+        path.reverse();
+        Some(path)
+    }
+    // Draws walls as '#' and every other cell with `open_cell`
+    fn render(&self, open_cell: impl Fn(Coord) -> char) -> String {
+        let (min, max) = self.bounds();
+        let mut string = String::new();
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                let coord = Coord { x, y };
+                string.push(if self.walls.contains(&coord) {
+                    '#'
+                } else {
+                    open_cell(coord)
+                });
+            }
+            string.push('\n');
+        }
+        string
+    }
+    fn landmark(&self, coord: Coord) -> Option<char> {
+        if coord == self.source {
+            Some('S')
+        } else if coord == self.start {
+            Some('D')
+        } else {
+            None
+        }
+    }
+    // One frame per minute until the maze is full
+    fn oxygen_frames(&self) -> Vec<String> {
+        let mut spread = OxygenSpread::new(self);
+        let mut frames = vec![spread.render()];
+        while spread.step() {
+            frames.push(spread.render());
+        }
+        frames
+    }
+    fn heatmap_ascii(&self) -> String {
+        let distances = self.distances_from(self.source);
+        let max = distances.values().cloned().max().unwrap_or(0).max(1);
+        self.render(|coord| match distances.get(&coord) {
+            Some(&distance) => std::char::from_digit((distance * 9 / max) as u32, 10).unwrap(),
+            None => ' ',
+        })
+    }
+    // Yellow close to the oxygen system fading to red furthest away
+    fn heatmap_ppm(&self, scale: usize) -> String {
+        let distances = self.distances_from(self.source);
+        let max = distances.values().cloned().max().unwrap_or(0).max(1);
+        let (min, max_coord) = self.bounds();
+        let width = (max_coord.x - min.x + 1) as usize;
+        let height = (max_coord.y - min.y + 1) as usize;
+        let mut string = format!("P3\n{} {}\n255\n", width * scale, height * scale);
+        for y in 0..height * scale {
+            let rgbs = (0..width * scale)
+                .map(|x| {
+                    let coord = Coord {
+                        x: min.x + (x / scale) as i32,
+                        y: min.y + (y / scale) as i32,
+                    };
+                    if self.walls.contains(&coord) {
+                        return "64 64 64".to_string();
                     }
-                    DroidStatus::MovedToGoal | DroidStatus::Moved => {
-                        if let DroidStatus::MovedToGoal = status {
-                            source = Some(next_destination);
-                        }
-                        droid.step(direction.opposite());
-                        queue.push_back(Node {
-                            destination: next_destination,
-                            path_from_start: {
-                                let mut path = path_from_start.clone();
-                                path.push(direction);
-                                path
-                            },
-                        });
+                    match distances.get(&coord) {
+                        Some(&distance) => format!("255 {} 0", 255 - distance * 255 / max),
+                        None => "0 0 0".to_string(),
                     }
+                })
+                .collect::<Vec<_>>();
+            string.push_str(&rgbs.join(" "));
+            string.push('\n');
+        }
+        string
+    }
+    fn route_ascii(&self, route: &[Coord]) -> String {
+        let route = route.iter().cloned().collect::<HashSet<_>>();
+        self.render(|coord| match self.landmark(coord) {
+            Some(landmark) => landmark,
+            None if route.contains(&coord) => '+',
+            None => ' ',
+        })
+    }
+}
+
+// The maze as oxygen fills it, one minute at a time
+struct OxygenSpread<'a> {
+    map: &'a Map,
+    filled: HashSet<Coord>,
+    // the cells oxygen reached in the latest minute
+    front: Vec<Coord>,
+}
+
+impl<'a> OxygenSpread<'a> {
+    fn new(map: &'a Map) -> Self {
+        let mut filled = HashSet::new();
+        filled.insert(map.source);
+        Self {
+            map,
+            filled,
+            front: vec![map.source],
+        }
+    }
+    // Oxygen flows from every filled cell into its open neighbours, but only the cells that
+    // filled last minute can have any that are still empty. Returns false once the maze is full.
+    fn step(&mut self) -> bool {
+        let mut front = Vec::new();
+        for &coord in &self.front {
+            for &direction in &ALL_DIRECTIONS {
+                let next_coord = coord + direction.to_unit_coord();
+                if !self.map.walls.contains(&next_coord) && self.filled.insert(next_coord) {
+                    front.push(next_coord);
                 }
             }
         }
-        droid.follow_directions(
-            path_from_start
-                .iter()
-                .rev()
-                .cloned()
-                .map(Direction::opposite),
-        );
+        if front.is_empty() {
+            return false;
+        }
+        self.front = front;
+        true
     }
-    Map {
-        source: source.unwrap(),
-        walls,
+    // Oxygen is 'O', except for the cells it reached this minute which are '*'
+    fn render(&self) -> String {
+        let front = self.front.iter().cloned().collect::<HashSet<_>>();
+        self.map.render(|coord| match self.map.landmark(coord) {
+            Some(landmark) => landmark,
+            None if front.contains(&coord) => '*',
+            None if self.filled.contains(&coord) => 'O',
+            None => ' ',
+        })
     }
 }
 
+fn write_heatmap(map: &Map, path: &Path, scale: usize) {
+    let contents = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("txt") => map.heatmap_ascii(),
+        Some("ppm") => map.heatmap_ppm(scale),
+        _ => panic!("unknown heatmap format: {}", path.display()),
+    };
+    std::fs::write(path, contents).unwrap();
+}
+
+fn explore_map(program: &[i128], explorer: &str) -> (Map, usize) {
+    let computer = Computer {
+        computer: IntcodeComputer::new(program),
+        input_buffer: IoBuffer::default(),
+        output_buffer: IoBuffer::default(),
+    };
+    let start = Pose {
+        position: ORIGIN,
+        facing: Direction::North,
+    };
+    let mut droid = Robot::<_, Droid>::new(computer, start);
+    let grid = match explorer {
+        "frontier" => robot::explore(&mut droid),
+        "backtrack" => robot::explore_backtracking(&mut droid),
+        "clone" => robot::explore_cloning(&droid),
+        "replay" => robot::explore_replaying(&mut droid),
+        other => panic!("unknown explorer: {}", other),
+    };
+    (Map::from_grid(&grid), grid.commands)
+}
+
 fn main() {
     let mut input_string = String::new();
     std::io::stdin()
@@ -634,7 +781,140 @@ fn main() {
         .split(",")
         .map(|s| s.trim().parse::<i128>().unwrap())
         .collect::<Vec<_>>();
-    let map = build_map(&program);
+    let mut explorer = None;
+    let mut frames_dir = None;
+    let mut heatmap_path = None;
+    let mut scale = 1;
+    let mut show_route = false;
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().expect("expected a value after the option");
+        match arg.as_str() {
+            "--frames" => frames_dir = Some(PathBuf::from(value())),
+            "--heatmap" => heatmap_path = Some(PathBuf::from(value())),
+            "--scale" => scale = value().parse::<usize>().expect("expected a scale factor"),
+            "--route" => show_route = true,
+            other => explorer = Some(other),
+        }
+    }
+    assert!(scale > 0, "scale must be positive");
+    let map = match explorer {
+        Some("compare") => {
+            let (map, replay_moves) = explore_map(&program, "replay");
+            println!("replay: {} droid moves", replay_moves);
+            for &explorer in &["frontier", "backtrack", "clone"] {
+                let (other_map, moves) = explore_map(&program, explorer);
+                assert_eq!(other_map, map);
+                println!("{}: {} droid moves", explorer, moves);
+            }
+            map
+        }
+        explorer => explore_map(&program, explorer.unwrap_or("backtrack")).0,
+    };
+    if let Some(frames_dir) = frames_dir {
+        std::fs::create_dir_all(&frames_dir).unwrap();
+        for (minute, frame) in map.oxygen_frames().iter().enumerate() {
+            let path = frames_dir.join(format!("minute{:03}.txt", minute));
+            std::fs::write(path, frame).unwrap();
+        }
+    }
+    if let Some(heatmap_path) = heatmap_path {
+        write_heatmap(&map, &heatmap_path, scale);
+    }
+    if show_route {
+        let route = map
+            .shortest_path(map.start, map.source)
+            .expect("no route to the oxygen system");
+        print!("{}", map.route_ascii(&route));
+        println!(
+            "shortest route to the oxygen system: {} moves",
+            route.len() - 1
+        );
+    }
     let longest_path_length = map.longest_path_length();
     println!("{}", longest_path_length);
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn program() -> Vec<i128> {
+        include_str!("../input")
+            .trim()
+            .split(',')
+            .map(|s| s.parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn explorers_agree_on_the_maze() {
+        let (map, _) = explore_map(&program(), "backtrack");
+        let route = map.shortest_path(map.start, map.source).unwrap();
+        assert_eq!(route.len() - 1, 236);
+        assert_eq!(map.longest_path_length(), 368);
+        for &explorer in &["frontier", "clone"] {
+            assert_eq!(explore_map(&program(), explorer).0, map);
+        }
+        let mut spread = OxygenSpread::new(&map);
+        let mut minutes = 0;
+        while spread.step() {
+            minutes += 1;
+        }
+        assert_eq!(minutes, 368);
+    }
+
+    #[test]
+    fn oxygen_spreads_one_cell_per_minute() {
+        let maze = "\
+######
+#S...#
+#.##.#
+#D####
+######
+";
+        let mut map = Map {
+            walls: HashSet::new(),
+            source: ORIGIN,
+            start: ORIGIN,
+        };
+        for (y, line) in maze.lines().enumerate() {
+            for (x, ch) in line.chars().enumerate() {
+                let coord = Coord {
+                    x: x as i32,
+                    y: y as i32,
+                };
+                match ch {
+                    '#' => {
+                        map.walls.insert(coord);
+                    }
+                    'S' => map.source = coord,
+                    'D' => map.start = coord,
+                    _ => (),
+                }
+            }
+        }
+        let frames = map.oxygen_frames();
+        assert_eq!(frames.len(), 5);
+        assert_eq!(frames[2], "######\n#SO* #\n#O## #\n#D####\n######\n");
+        assert_eq!(frames[4], "######\n#SOOO#\n#O##*#\n#D####\n######\n");
+        assert_eq!(
+            map.heatmap_ascii(),
+            "######\n#0246#\n#2##9#\n#4####\n######\n"
+        );
+        let ppm = map.heatmap_ppm(2);
+        let lines = ppm.lines().collect::<Vec<_>>();
+        assert_eq!(&lines[..3], &["P3", "12 10", "255"]);
+        assert_eq!(lines.len(), 3 + 10);
+        assert!(lines[3..]
+            .iter()
+            .all(|line| line.split(' ').count() == 12 * 3));
+        let route = map.shortest_path(map.start, map.source).unwrap();
+        assert_eq!(route.len() - 1, 2);
+        assert_eq!(
+            map.route_ascii(&route),
+            "######\n#S   #\n#+## #\n#D####\n######\n"
+        );
+    }
+}
@@ -1,5 +1,7 @@
 use std::collections::{HashSet, VecDeque};
-use std::io::Read;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
 #[derive(Debug)]
 enum ParamMode {
@@ -430,166 +432,470 @@ impl IntcodeComputer {
     }
 }
 
-#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
-struct Coord {
-    x: i32,
-    y: i32,
-}
+mod scaffold {
+    use std::collections::{HashSet, VecDeque};
 
-impl std::ops::Add for Coord {
-    type Output = Self;
-    fn add(self, rhs: Self) -> Self {
-        Self {
-            x: self.x + rhs.x,
-            y: self.y + rhs.y,
+    #[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
+    pub struct Coord {
+        pub x: i32,
+        pub y: i32,
+    }
+
+    impl std::ops::Add for Coord {
+        type Output = Self;
+        fn add(self, rhs: Self) -> Self {
+            Self {
+                x: self.x + rhs.x,
+                y: self.y + rhs.y,
+            }
         }
     }
-}
 
-const UNIT_COORDS: [Coord; 4] = [
-    Coord { x: 0, y: 1 },
-    Coord { x: 0, y: -1 },
-    Coord { x: 1, y: 0 },
-    Coord { x: -1, y: 0 },
-];
+    pub const UNIT_COORDS: [Coord; 4] = [
+        Coord { x: 0, y: 1 },
+        Coord { x: 0, y: -1 },
+        Coord { x: 1, y: 0 },
+        Coord { x: -1, y: 0 },
+    ];
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-enum Cell {
-    Empty,
-    Scaffold,
-}
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub enum Cell {
+        Empty,
+        Scaffold,
+    }
 
-struct Grid {
-    cells: Vec<Cell>,
-    width: usize,
-    height: usize,
-}
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub enum ScaffoldKind {
+        Isolated,
+        DeadEnd,
+        Straight,
+        Corner,
+        Junction,
+        Intersection,
+    }
 
-impl Grid {
-    fn get(&self, Coord { x, y }: Coord) -> Option<Cell> {
-        if x < 0 || x >= self.width as i32 || y < 0 || y >= self.height as i32 {
-            return None;
-        }
-        let index = y as usize * self.width + x as usize;
-        Some(self.cells[index])
+    #[derive(Debug, PartialEq, Eq)]
+    pub enum Traversal {
+        Circuit,
+        Path(Coord, Coord),
+        Disconnected,
+        TooManyEnds(Vec<Coord>),
     }
-}
 
-#[derive(Clone, Copy, Debug)]
-#[repr(u8)]
-enum Direction {
-    North = 1,
-    South = 2,
-    West = 3,
-    East = 4,
-}
-
-const ALL_DIRECTIONS: [Direction; 4] = [
-    Direction::North,
-    Direction::South,
-    Direction::West,
-    Direction::East,
-];
+    impl Traversal {
+        pub fn can_start_at(&self, coord: Coord) -> bool {
+            match self {
+                Traversal::Circuit => true,
+                Traversal::Path(start, end) => coord == *start || coord == *end,
+                Traversal::Disconnected | Traversal::TooManyEnds(_) => false,
+            }
+        }
+    }
 
-impl Direction {
-    fn to_i128(self) -> i128 {
-        self as i128
+    #[derive(Debug)]
+    pub struct Grid {
+        cells: Vec<Cell>,
+        pub width: usize,
+        pub height: usize,
     }
-    fn to_unit_coord(self) -> Coord {
-        match self {
-            Self::North => Coord { x: 0, y: -1 },
-            Self::South => Coord { x: 0, y: 1 },
-            Self::West => Coord { x: -1, y: 0 },
-            Self::East => Coord { x: 1, y: 0 },
+
+    impl Grid {
+        pub fn get(&self, Coord { x, y }: Coord) -> Option<Cell> {
+            if x < 0 || x >= self.width as i32 || y < 0 || y >= self.height as i32 {
+                return None;
+            }
+            let index = y as usize * self.width + x as usize;
+            Some(self.cells[index])
+        }
+        fn is_scaffold(&self, coord: Coord) -> bool {
+            self.get(coord) == Some(Cell::Scaffold)
+        }
+        pub fn scaffold_coords(&self) -> impl Iterator<Item = Coord> + '_ {
+            let width = self.width;
+            (0..self.cells.len())
+                .map(move |index| Coord {
+                    x: (index % width) as i32,
+                    y: (index / width) as i32,
+                })
+                .filter(move |&coord| self.is_scaffold(coord))
+        }
+        fn degree(&self, coord: Coord) -> usize {
+            UNIT_COORDS
+                .iter()
+                .filter(|&&unit_coord| self.is_scaffold(coord + unit_coord))
+                .count()
+        }
+        pub fn classify(&self, coord: Coord) -> Option<ScaffoldKind> {
+            if !self.is_scaffold(coord) {
+                return None;
+            }
+            let directions = ALL_DIRECTIONS
+                .iter()
+                .cloned()
+                .filter(|direction| self.is_scaffold(coord + direction.to_unit_coord()))
+                .collect::<Vec<_>>();
+            Some(match directions.as_slice() {
+                [] => ScaffoldKind::Isolated,
+                [_] => ScaffoldKind::DeadEnd,
+                &[a, b] if a.opposite() == b => ScaffoldKind::Straight,
+                [_, _] => ScaffoldKind::Corner,
+                [_, _, _] => ScaffoldKind::Junction,
+                _ => ScaffoldKind::Intersection,
+            })
+        }
+        pub fn cells_of_kind(&self, kind: ScaffoldKind) -> Vec<Coord> {
+            self.scaffold_coords()
+                .filter(|&coord| self.classify(coord) == Some(kind))
+                .collect()
         }
+        pub fn intersections(&self) -> Vec<Coord> {
+            self.cells_of_kind(ScaffoldKind::Intersection)
+        }
+        pub fn alignment_parameter(&self) -> i32 {
+            self.intersections()
+                .iter()
+                .map(|Coord { x, y }| x * y)
+                .sum()
+        }
+        pub fn num_edges(&self) -> usize {
+            self.scaffold_coords()
+                .map(|coord| self.degree(coord))
+                .sum::<usize>()
+                / 2
+        }
+        // Whether there's a walk along the scaffold crossing every piece of it exactly once
+        pub fn traversal(&self) -> Traversal {
+            let first = match self.scaffold_coords().next() {
+                Some(first) => first,
+                None => return Traversal::Circuit,
+            };
+            let mut seen = HashSet::new();
+            let mut queue = VecDeque::new();
+            seen.insert(first);
+            queue.push_back(first);
+            while let Some(coord) = queue.pop_front() {
+                for &unit_coord in &UNIT_COORDS {
+                    let neighbour = coord + unit_coord;
+                    if self.is_scaffold(neighbour) && seen.insert(neighbour) {
+                        queue.push_back(neighbour);
+                    }
+                }
+            }
+            if seen.len() != self.scaffold_coords().count() {
+                return Traversal::Disconnected;
+            }
+            let ends = self
+                .scaffold_coords()
+                .filter(|&coord| self.degree(coord) % 2 == 1)
+                .collect::<Vec<_>>();
+            match ends.as_slice() {
+                [] => Traversal::Circuit,
+                &[start, end] => Traversal::Path(start, end),
+                _ => Traversal::TooManyEnds(ends),
+            }
+        }
+    }
+
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    #[repr(u8)]
+    pub enum Direction {
+        North = 1,
+        South = 2,
+        West = 3,
+        East = 4,
     }
-    fn turn(self, turn: Turn) -> Self {
-        match (self, turn) {
-            (Self::North, Turn::Left) => Self::West,
-            (Self::North, Turn::Right) => Self::East,
-            (Self::East, Turn::Left) => Self::North,
-            (Self::East, Turn::Right) => Self::South,
-            (Self::South, Turn::Left) => Self::East,
-            (Self::South, Turn::Right) => Self::West,
-            (Self::West, Turn::Left) => Self::South,
-            (Self::West, Turn::Right) => Self::North,
+
+    pub const ALL_DIRECTIONS: [Direction; 4] = [
+        Direction::North,
+        Direction::South,
+        Direction::West,
+        Direction::East,
+    ];
+
+    impl Direction {
+        pub fn to_i128(self) -> i128 {
+            self as i128
+        }
+        pub fn to_unit_coord(self) -> Coord {
+            match self {
+                Self::North => Coord { x: 0, y: -1 },
+                Self::South => Coord { x: 0, y: 1 },
+                Self::West => Coord { x: -1, y: 0 },
+                Self::East => Coord { x: 1, y: 0 },
+            }
+        }
+        pub fn turn(self, turn: Turn) -> Self {
+            match (self, turn) {
+                (Self::North, Turn::Left) => Self::West,
+                (Self::North, Turn::Right) => Self::East,
+                (Self::East, Turn::Left) => Self::North,
+                (Self::East, Turn::Right) => Self::South,
+                (Self::South, Turn::Left) => Self::East,
+                (Self::South, Turn::Right) => Self::West,
+                (Self::West, Turn::Left) => Self::South,
+                (Self::West, Turn::Right) => Self::North,
+            }
+        }
+        pub fn opposite(self) -> Self {
+            match self {
+                Self::North => Self::South,
+                Self::South => Self::North,
+                Self::East => Self::West,
+                Self::West => Self::East,
+            }
         }
     }
-    fn opposite(self) -> Self {
-        match self {
-            Self::North => Self::South,
-            Self::South => Self::North,
-            Self::East => Self::West,
-            Self::West => Self::East,
-        }
-    }
-}
-
-#[derive(Debug, Clone, Copy)]
-struct Robot {
-    location: Coord,
-    facing: Direction,
-}
-
-fn parse_grid(s: &str) -> (Grid, Robot) {
-    let mut width = 0;
-    let mut height = 0;
-    let mut cells = Vec::with_capacity(s.len());
-    let mut robot = None;
-    for (y, line) in s.lines().enumerate() {
-        if line.len() == 0 {
-            break;
-        }
-        width = line.len();
-        for (x, c) in line.chars().enumerate() {
-            cells.push(match c {
-                '#' => Cell::Scaffold,
-                '^' | '<' | '>' | 'v' => {
-                    let facing = match c {
-                        '^' => Direction::North,
-                        '>' => Direction::East,
-                        'v' => Direction::South,
-                        '<' => Direction::West,
-                        _ => unreachable!(),
-                    };
-                    robot = Some(Robot {
-                        location: Coord {
-                            x: x as i32,
-                            y: y as i32,
-                        },
-                        facing,
-                    });
-                    Cell::Scaffold
+
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub enum Turn {
+        Left,
+        Right,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Robot {
+        pub location: Coord,
+        pub facing: Direction,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum RobotView {
+        OnScaffold(Robot),
+        Tumbling(Coord),
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    pub enum ParseError {
+        UnexpectedChar { ch: char, coord: Coord },
+        RaggedLine { y: usize },
+        NoRobot,
+        MultipleRobots,
+    }
+
+    impl std::fmt::Display for ParseError {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            match self {
+                ParseError::UnexpectedChar { ch, coord } => write!(
+                    f,
+                    "unexpected character {:?} at ({}, {})",
+                    ch, coord.x, coord.y
+                ),
+                ParseError::RaggedLine { y } => {
+                    write!(f, "line {} differs in length from the first line", y)
                 }
-                '.' => Cell::Empty,
-                _ => panic!(),
-            });
+                ParseError::NoRobot => write!(f, "no robot in the camera image"),
+                ParseError::MultipleRobots => write!(f, "more than one robot in the camera image"),
+            }
         }
-        height += 1;
     }
-    (
-        Grid {
-            width,
+
+    pub fn parse_grid(s: &str) -> Result<(Grid, RobotView), ParseError> {
+        let mut width = None;
+        let mut height = 0;
+        let mut cells = Vec::with_capacity(s.len());
+        let mut robot = None;
+        for (y, line) in s.lines().enumerate() {
+            if line.is_empty() {
+                break;
+            }
+            if *width.get_or_insert(line.len()) != line.len() {
+                return Err(ParseError::RaggedLine { y });
+            }
+            for (x, ch) in line.chars().enumerate() {
+                let coord = Coord {
+                    x: x as i32,
+                    y: y as i32,
+                };
+                let (cell, view) = match ch {
+                    '#' => (Cell::Scaffold, None),
+                    '.' => (Cell::Empty, None),
+                    // the robot has fallen off the scaffold
+                    'X' => (Cell::Empty, Some(RobotView::Tumbling(coord))),
+                    '^' | '<' | '>' | 'v' => {
+                        let facing = match ch {
+                            '^' => Direction::North,
+                            '>' => Direction::East,
+                            'v' => Direction::South,
+                            '<' => Direction::West,
+                            _ => unreachable!(),
+                        };
+                        let robot = Robot {
+                            location: coord,
+                            facing,
+                        };
+                        (Cell::Scaffold, Some(RobotView::OnScaffold(robot)))
+                    }
+                    _ => return Err(ParseError::UnexpectedChar { ch, coord }),
+                };
+                if let Some(view) = view {
+                    if robot.replace(view).is_some() {
+                        return Err(ParseError::MultipleRobots);
+                    }
+                }
+                cells.push(cell);
+            }
+            height += 1;
+        }
+        let robot = robot.ok_or(ParseError::NoRobot)?;
+        let grid = Grid {
+            width: width.unwrap_or(0),
             height,
             cells,
-        },
-        robot.unwrap(),
-    )
-}
+        };
+        Ok((grid, robot))
+    }
 
-#[derive(Clone, Copy, Debug)]
-enum Turn {
-    Left,
-    Right,
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        const CALIBRATION: &str = "\
+..#..........
+..#..........
+#######...###
+#.#...#...#.#
+#############
+..#...#...#..
+..#####...^..
+";
+
+        const MOVEMENT: &str = "\
+#######...#####
+#.....#...#...#
+#.....#...#...#
+......#...#...#
+......#...###.#
+......#.....#.#
+^########...#.#
+......#.#...#.#
+......#########
+........#...#..
+....#########..
+....#...#......
+....#...#......
+....#...#......
+....#####......
+";
+
+        fn coord(x: i32, y: i32) -> Coord {
+            Coord { x, y }
+        }
+
+        #[test]
+        fn calibration_example() {
+            let (grid, robot) = parse_grid(CALIBRATION).unwrap();
+            assert_eq!((grid.width, grid.height), (13, 7));
+            assert_eq!(
+                robot,
+                RobotView::OnScaffold(Robot {
+                    location: coord(10, 6),
+                    facing: Direction::North,
+                })
+            );
+            assert_eq!(
+                grid.intersections(),
+                vec![coord(2, 2), coord(2, 4), coord(6, 4), coord(10, 4)]
+            );
+            assert_eq!(grid.alignment_parameter(), 76);
+            assert_eq!(
+                grid.cells_of_kind(ScaffoldKind::DeadEnd),
+                vec![coord(2, 0), coord(10, 6)]
+            );
+            assert_eq!(grid.classify(coord(0, 2)), Some(ScaffoldKind::Corner));
+            assert_eq!(grid.classify(coord(4, 4)), Some(ScaffoldKind::Straight));
+            assert_eq!(grid.classify(coord(1, 3)), None);
+            assert_eq!(grid.traversal(), Traversal::Path(coord(2, 0), coord(10, 6)));
+        }
+
+        #[test]
+        fn movement_example() {
+            let (grid, robot) = parse_grid(MOVEMENT).unwrap();
+            assert_eq!(
+                robot,
+                RobotView::OnScaffold(Robot {
+                    location: coord(0, 6),
+                    facing: Direction::North,
+                })
+            );
+            assert_eq!(
+                grid.cells_of_kind(ScaffoldKind::DeadEnd),
+                vec![coord(0, 2), coord(0, 6)]
+            );
+            assert!(grid.cells_of_kind(ScaffoldKind::Junction).is_empty());
+            let traversal = grid.traversal();
+            assert_eq!(traversal, Traversal::Path(coord(0, 2), coord(0, 6)));
+            assert!(traversal.can_start_at(coord(0, 6)));
+            assert!(!traversal.can_start_at(coord(1, 6)));
+        }
+
+        #[test]
+        fn robot_glyphs() {
+            for &(ch, facing) in &[
+                ('^', Direction::North),
+                ('v', Direction::South),
+                ('<', Direction::West),
+                ('>', Direction::East),
+            ] {
+                let (grid, robot) = parse_grid(&format!("#{}#\n", ch)).unwrap();
+                assert_eq!(
+                    robot,
+                    RobotView::OnScaffold(Robot {
+                        location: coord(1, 0),
+                        facing,
+                    })
+                );
+                assert_eq!(grid.get(coord(1, 0)), Some(Cell::Scaffold));
+            }
+            let (grid, robot) = parse_grid("###\n.X.\n").unwrap();
+            assert_eq!(robot, RobotView::Tumbling(coord(1, 1)));
+            assert_eq!(grid.get(coord(1, 1)), Some(Cell::Empty));
+        }
+
+        #[test]
+        fn parse_errors() {
+            assert_eq!(parse_grid("###\n").err(), Some(ParseError::NoRobot));
+            assert_eq!(
+                parse_grid("#^#\n.X.\n").err(),
+                Some(ParseError::MultipleRobots)
+            );
+            assert_eq!(
+                parse_grid("#^#\n..\n").err(),
+                Some(ParseError::RaggedLine { y: 1 })
+            );
+            assert_eq!(
+                parse_grid("#^?\n").err(),
+                Some(ParseError::UnexpectedChar {
+                    ch: '?',
+                    coord: coord(2, 0)
+                })
+            );
+        }
+
+        #[test]
+        fn traversal_of_shapes() {
+            let (grid, _) = parse_grid("^##\n#.#\n###\n").unwrap();
+            assert_eq!(grid.traversal(), Traversal::Circuit);
+            let (grid, _) = parse_grid("^.#\n").unwrap();
+            assert_eq!(grid.traversal(), Traversal::Disconnected);
+            let (grid, _) = parse_grid(".#.\n#^#\n.#.\n").unwrap();
+            assert_eq!(grid.classify(coord(1, 1)), Some(ScaffoldKind::Intersection));
+            assert_eq!(
+                grid.traversal(),
+                Traversal::TooManyEnds(vec![coord(1, 0), coord(0, 1), coord(2, 1), coord(1, 2)])
+            );
+        }
+    }
 }
 
+use scaffold::{Cell, Coord, Grid, Robot, RobotView, ScaffoldKind, Turn};
+
 #[derive(Clone, Copy, Debug)]
 enum Step {
     Turn(Turn),
     Forward,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 enum CompressedStep {
     Turn(Turn),
     Forward(usize),
@@ -613,28 +919,185 @@ fn compress_steps(steps: &[Step]) -> Vec<CompressedStep> {
     compressed_steps
 }
 
-fn full_step_sequence(grid: &Grid, mut robot: Robot) -> Vec<Step> {
-    let mut steps = Vec::new();
-    loop {
-        let next_coord = robot.location + robot.facing.to_unit_coord();
-        if let Some(Cell::Scaffold) = grid.get(next_coord) {
-            steps.push(Step::Forward);
-            robot.location = next_coord;
-            continue;
+fn scaffold_edge(a: Coord, b: Coord) -> (Coord, Coord) {
+    if (a.x, a.y) < (b.x, b.y) {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+struct ScaffoldWalk<'a> {
+    grid: &'a Grid,
+    visited: HashSet<(Coord, Coord)>,
+    steps: Vec<Step>,
+}
+
+const TURNS: &[&[Turn]] = &[&[], &[Turn::Left], &[Turn::Right]];
+// The robot may start facing away from the only scaffold next to it
+const START_TURNS: &[&[Turn]] = &[
+    &[],
+    &[Turn::Left],
+    &[Turn::Right],
+    &[Turn::Left, Turn::Left],
+];
+
+impl<'a> ScaffoldWalk<'a> {
+    // Visits every walk which crosses each piece of scaffold exactly once, without ever
+    // reversing except at the start. At each cell, going straight ahead is tried before
+    // turning, so the first walk visited is the one which goes straight through every
+    // intersection.
+    fn visit<T>(
+        &mut self,
+        robot: Robot,
+        edges_remaining: usize,
+        f: &mut dyn FnMut(&[Step]) -> Option<T>,
+    ) -> Option<T> {
+        if edges_remaining == 0 {
+            return f(&self.steps);
         }
-        let left_coord = robot.location + robot.facing.turn(Turn::Left).to_unit_coord();
-        if let Some(Cell::Scaffold) = grid.get(left_coord) {
-            steps.push(Step::Turn(Turn::Left));
-            robot.facing = robot.facing.turn(Turn::Left);
-            continue;
+        let turn_choices = if self.steps.is_empty() {
+            START_TURNS
+        } else {
+            TURNS
+        };
+        for &turns in turn_choices {
+            let facing = turns
+                .iter()
+                .fold(robot.facing, |facing, &turn| facing.turn(turn));
+            let location = robot.location + facing.to_unit_coord();
+            if self.grid.get(location) != Some(Cell::Scaffold) {
+                continue;
+            }
+            let edge = scaffold_edge(robot.location, location);
+            if !self.visited.insert(edge) {
+                continue;
+            }
+            let num_steps = self.steps.len();
+            self.steps
+                .extend(turns.iter().map(|&turn| Step::Turn(turn)));
+            self.steps.push(Step::Forward);
+            let result = self.visit(Robot { location, facing }, edges_remaining - 1, f);
+            self.steps.truncate(num_steps);
+            self.visited.remove(&edge);
+            if result.is_some() {
+                return result;
+            }
         }
-        let right_coord = robot.location + robot.facing.turn(Turn::Right).to_unit_coord();
-        if let Some(Cell::Scaffold) = grid.get(right_coord) {
-            steps.push(Step::Turn(Turn::Right));
-            robot.facing = robot.facing.turn(Turn::Right);
-            continue;
+        None
+    }
+}
+
+fn find_scaffold_walk<T>(
+    grid: &Grid,
+    robot: Robot,
+    mut f: impl FnMut(&[Step]) -> Option<T>,
+) -> Option<T> {
+    let mut walk = ScaffoldWalk {
+        grid,
+        visited: HashSet::new(),
+        steps: Vec::new(),
+    };
+    walk.visit(robot, grid.num_edges(), &mut f)
+}
+
+const MAX_ROUTINE_LENGTH: usize = 20;
+const FUNCTION_NAMES: [char; 3] = ['A', 'B', 'C'];
+
+impl std::fmt::Display for CompressedStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CompressedStep::Turn(Turn::Left) => write!(f, "L"),
+            CompressedStep::Turn(Turn::Right) => write!(f, "R"),
+            CompressedStep::Forward(distance) => write!(f, "{}", distance),
         }
-        return steps;
+    }
+}
+
+fn encode_steps(steps: &[CompressedStep]) -> String {
+    steps
+        .iter()
+        .map(|step| step.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[derive(Debug)]
+struct MovementRoutines {
+    main: Vec<usize>,
+    functions: Vec<Vec<CompressedStep>>,
+}
+
+impl MovementRoutines {
+    fn main_string(&self) -> String {
+        let calls = self
+            .main
+            .iter()
+            .map(|&index| FUNCTION_NAMES[index].to_string())
+            .collect::<Vec<_>>();
+        format!("{}\n", calls.join(","))
+    }
+    fn function_strings(&self) -> Vec<String> {
+        // The robot always asks for every function, so unused ones get a placeholder
+        (0..FUNCTION_NAMES.len())
+            .map(|index| match self.functions.get(index) {
+                Some(function) => format!("{}\n", encode_steps(function)),
+                None => "L\n".to_string(),
+            })
+            .collect()
+    }
+}
+
+fn search_movement_routines<'a>(
+    steps: &'a [CompressedStep],
+    functions: &mut Vec<&'a [CompressedStep]>,
+    main: &mut Vec<usize>,
+) -> bool {
+    if steps.is_empty() {
+        return true;
+    }
+    // each call in the main routine takes a name and a comma
+    if (main.len() + 1) * 2 - 1 > MAX_ROUTINE_LENGTH {
+        return false;
+    }
+    for index in 0..functions.len() {
+        let function = functions[index];
+        if steps.starts_with(function) {
+            main.push(index);
+            if search_movement_routines(&steps[function.len()..], functions, main) {
+                return true;
+            }
+            main.pop();
+        }
+    }
+    if functions.len() < FUNCTION_NAMES.len() {
+        for length in 1..=steps.len() {
+            let function = &steps[..length];
+            if encode_steps(function).len() > MAX_ROUTINE_LENGTH {
+                break;
+            }
+            main.push(functions.len());
+            functions.push(function);
+            if search_movement_routines(&steps[length..], functions, main) {
+                return true;
+            }
+            functions.pop();
+            main.pop();
+        }
+    }
+    false
+}
+
+fn find_movement_routines(steps: &[CompressedStep]) -> Option<MovementRoutines> {
+    let mut functions = Vec::new();
+    let mut main = Vec::new();
+    if search_movement_routines(steps, &mut functions, &mut main) {
+        Some(MovementRoutines {
+            main,
+            functions: functions.iter().map(|function| function.to_vec()).collect(),
+        })
+    } else {
+        None
     }
 }
 
@@ -694,27 +1157,126 @@ impl InstructRobot {
     }
 }
 
-const MAIN: &str = "A,B,A,C,B,C,B,C,A,C\n";
-const PROG_A: &str = "L,10,R,12,R,12\n";
-const PROG_B: &str = "R,6,R,10,L,10\n";
-const PROG_C: &str = "R,10,L,10,L,12,R,6\n";
+struct VideoFeed {
+    frame_duration: Duration,
+    dump_dir: Option<PathBuf>,
+}
+
+impl VideoFeed {
+    fn parse(args: &[String]) -> Self {
+        let mut feed = VideoFeed {
+            frame_duration: Duration::from_millis(100),
+            dump_dir: None,
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let value = args.next().expect("expected a value after the option");
+            match arg.as_str() {
+                "--fps" => {
+                    let fps = value
+                        .parse::<f64>()
+                        .expect("expected a number of frames per second");
+                    feed.frame_duration = Duration::from_secs_f64(1.0 / fps);
+                }
+                "--dump" => feed.dump_dir = Some(PathBuf::from(value)),
+                other => panic!("unknown option: {}", other),
+            }
+        }
+        feed
+    }
+}
+
+struct FramePlayer<'a> {
+    feed: &'a VideoFeed,
+    num_frames: usize,
+    next_frame_time: Instant,
+}
+
+impl<'a> FramePlayer<'a> {
+    fn new(feed: &'a VideoFeed) -> Self {
+        if let Some(dump_dir) = feed.dump_dir.as_ref() {
+            std::fs::create_dir_all(dump_dir).unwrap();
+        }
+        // clear the screen once, then redraw each frame over the top of the previous one
+        print!("\x1b[2J");
+        Self {
+            feed,
+            num_frames: 0,
+            next_frame_time: Instant::now(),
+        }
+    }
+    fn show(&mut self, frame: &str) {
+        if let Some(dump_dir) = self.feed.dump_dir.as_ref() {
+            let path = dump_dir.join(format!("frame{:05}.txt", self.num_frames));
+            std::fs::write(path, frame).unwrap();
+        }
+        let now = Instant::now();
+        if self.next_frame_time > now {
+            std::thread::sleep(self.next_frame_time - now);
+        }
+        self.next_frame_time = Instant::now() + self.feed.frame_duration;
+        print!("\x1b[H{}", frame);
+        println!("frame {}", self.num_frames);
+        std::io::stdout().flush().unwrap();
+        self.num_frames += 1;
+    }
+}
+
+// Frames are separated by blank lines; blank frames are dropped
+fn split_frames(output: &str) -> Vec<&str> {
+    let mut frames = Vec::new();
+    let mut start = 0;
+    for (index, ch) in output.char_indices() {
+        if ch == '\n' && (index == start || output[start..index].ends_with('\n')) {
+            frames.push(&output[start..index]);
+            start = index + 1;
+        }
+    }
+    frames.push(&output[start..]);
+    frames.retain(|frame| !frame.trim().is_empty());
+    frames
+}
 
-fn instruct_robot(program: &[i128]) {
+fn play_video_feed(robot: &mut InstructRobot, feed: &VideoFeed) -> i128 {
+    let mut output = String::new();
+    let dust = loop {
+        match robot.run() {
+            StopStatus::Halt => panic!("unexpected halt"),
+            StopStatus::WaitForInput => panic!("unexpected wait for input"),
+            StopStatus::WroteOutput => {
+                let value = robot.output_buffer.read().unwrap();
+                if value > 127 {
+                    break value;
+                }
+                output.push(value as u8 as char);
+            }
+        }
+    };
+    let mut player = FramePlayer::new(feed);
+    for frame in split_frames(&output) {
+        player.show(frame);
+    }
+    dust
+}
+
+fn instruct_robot(program: &[i128], routines: &MovementRoutines, video: Option<&VideoFeed>) {
     let mut robot = InstructRobot::new(program);
     robot.run_until_wait_for_input();
     println!("{}", robot.drain_ascii_string());
-    robot.input_str(MAIN);
-    robot.run_until_wait_for_input();
-    println!("{}", robot.drain_ascii_string());
-    robot.input_str(PROG_A);
-    robot.run_until_wait_for_input();
-    println!("{}", robot.drain_ascii_string());
-    robot.input_str(PROG_B);
-    robot.run_until_wait_for_input();
-    println!("{}", robot.drain_ascii_string());
-    robot.input_str(PROG_C);
+    robot.input_str(&routines.main_string());
+    for function in routines.function_strings() {
+        robot.run_until_wait_for_input();
+        println!("{}", robot.drain_ascii_string());
+        robot.input_str(&function);
+    }
     robot.run_until_wait_for_input();
     println!("{}", robot.drain_ascii_string());
+    if let Some(feed) = video {
+        robot.input_str("y\n");
+        let dust = play_video_feed(&mut robot, feed);
+        println!("{}", dust);
+        return;
+    }
     robot.input_str("n\n");
     loop {
         match robot.run() {
@@ -742,31 +1304,36 @@ fn main() {
         .split(",")
         .map(|s| s.trim().parse::<i128>().unwrap())
         .collect::<Vec<_>>();
+    let args = std::env::args().collect::<Vec<_>>();
+    let video = match args.get(1).map(String::as_str) {
+        Some("video") => Some(VideoFeed::parse(&args[2..])),
+        Some(other) => panic!("unknown mode: {}", other),
+        None => None,
+    };
     let map_string = build_string_map(&program);
-    let (map_grid, robot) = parse_grid(&map_string);
-    let mut intersections = HashSet::new();
-    for i in 0..map_grid.height {
-        'inner: for j in 0..map_grid.width {
-            let coord = Coord {
-                x: j as i32,
-                y: i as i32,
-            };
-            if map_grid.get(coord).unwrap() == Cell::Scaffold {
-                for &unit_coord in &UNIT_COORDS {
-                    match map_grid.get(coord + unit_coord) {
-                        Some(Cell::Scaffold) => (),
-                        _ => continue 'inner,
-                    }
-                }
-                intersections.insert(coord);
-            }
-        }
+    let (map_grid, robot) = match scaffold::parse_grid(&map_string) {
+        Ok((map_grid, RobotView::OnScaffold(robot))) => (map_grid, robot),
+        Ok((_, RobotView::Tumbling(coord))) => panic!("robot is tumbling at {:?}", coord),
+        Err(error) => panic!("{}", error),
+    };
+    println!("alignment parameter: {}", map_grid.alignment_parameter());
+    println!(
+        "dead ends: {:?}",
+        map_grid.cells_of_kind(ScaffoldKind::DeadEnd)
+    );
+    let traversal = map_grid.traversal();
+    println!("traversal: {:?}", traversal);
+    if !traversal.can_start_at(robot.location) {
+        panic!("the scaffold can't be traversed in one pass from the robot's location");
     }
-    let full_steps = full_step_sequence(&map_grid, robot);
-    let compressed_steps = compress_steps(&full_steps);
+    let (compressed_steps, routines) = find_scaffold_walk(&map_grid, robot, |steps| {
+        let compressed_steps = compress_steps(steps);
+        find_movement_routines(&compressed_steps).map(|routines| (compressed_steps, routines))
+    })
+    .expect("no walk over the scaffold can be compressed into movement routines");
     // modify the program to instruct the robot
     program[0] = 2;
-    instruct_robot(&program);
+    instruct_robot(&program, &routines, video.as_ref());
     for &compressed_step in &compressed_steps {
         match compressed_step {
             CompressedStep::Forward(distance) => print!("{}, ", distance),
@@ -776,30 +1343,169 @@ fn main() {
     }
 }
 
-// L, 10, R, 12, R, 12, R, 6, R, 10, L, 10, L, 10, R, 12, R, 12, R, 10, L, 10, L, 12, R, 6, R, 6,
-// R, 10, L, 10, R, 10, L, 10, L, 12, R, 6, R, 6, R, 10, L, 10, R, 10, L, 10, L, 12, R, 6, L, 10,
-// R, 12, R, 12, R, 10, L, 10, L, 12, R, 6,
-//
-// ########
-//
-// A: L, 10, R, 12, R, 12,
-// B: R, 6, R, 10, L, 10,
-// A: L, 10, R, 12, R, 12,
-// C: R, 10, L, 10, L, 12, R, 6,
-// B: R, 6, R, 10, L, 10,
-// C: R, 10, L, 10, L, 12, R, 6,
-// B: R, 6, R, 10, L, 10,
-// C: R, 10, L, 10, L, 12, R, 6,
-// A: L, 10, R, 12, R, 12,
-// C: R, 10, L, 10, L, 12, R, 6,
-//
-// ABACBCBCAC
-//
-// ########
-//
-//  A: L, 10, R, 12, R, 12,
-//
-//  B: R, 6, R, 10, L, 10,
-//
-//  C: R, 10, L, 10, L, 12, R, 6,
-//
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn walk(image: &str) -> Option<String> {
+        let (grid, robot) = scaffold::parse_grid(image).unwrap();
+        let robot = match robot {
+            RobotView::OnScaffold(robot) => robot,
+            RobotView::Tumbling(_) => panic!("robot is tumbling"),
+        };
+        find_scaffold_walk(&grid, robot, |steps| {
+            Some(encode_steps(&compress_steps(steps)))
+        })
+    }
+
+    #[test]
+    fn walk_starting_facing_away_from_the_scaffold() {
+        let image = "\
+..#..
+..#..
+..v..
+";
+        assert_eq!(walk(image).as_deref(), Some("L,L,2"));
+    }
+
+    #[test]
+    fn walk_only_reverses_at_the_start() {
+        let image = "\
+#####
+#...#
+^...#
+";
+        assert_eq!(walk(image).as_deref(), Some("2,R,4,R,2"));
+        let image = "\
+..#..
+..#..
+^####
+";
+        assert_eq!(walk(image), None);
+    }
+
+    fn parse_steps(steps: &str) -> Vec<CompressedStep> {
+        steps
+            .split(',')
+            .map(|step| match step {
+                "L" => CompressedStep::Turn(Turn::Left),
+                "R" => CompressedStep::Turn(Turn::Right),
+                distance => CompressedStep::Forward(distance.parse().unwrap()),
+            })
+            .collect()
+    }
+
+    fn expand(routines: &MovementRoutines) -> Vec<CompressedStep> {
+        routines
+            .main
+            .iter()
+            .flat_map(|&index| routines.functions[index].iter().copied())
+            .collect()
+    }
+
+    fn check_routines(steps: &[CompressedStep], routines: &MovementRoutines) {
+        assert!(routines.main_string().len() <= MAX_ROUTINE_LENGTH + 1);
+        let functions = routines.function_strings();
+        assert_eq!(functions.len(), FUNCTION_NAMES.len());
+        for function in &functions {
+            assert!(function.len() <= MAX_ROUTINE_LENGTH + 1, "{:?}", function);
+        }
+        assert_eq!(expand(routines), steps);
+    }
+
+    #[test]
+    fn movement_routines_example() {
+        let steps = parse_steps("R,8,R,8,R,4,R,4,R,8,L,6,L,2,R,4,R,4,R,8,R,8,R,8,L,6,L,2");
+        let routines = find_movement_routines(&steps).unwrap();
+        check_routines(&steps, &routines);
+    }
+
+    #[test]
+    fn movement_routines_need_at_most_three_functions() {
+        // Every pair of steps is different, so three functions are too short to cover them
+        let steps = (1..=30)
+            .map(|distance| format!("L,{}", distance))
+            .collect::<Vec<_>>()
+            .join(",");
+        assert!(find_movement_routines(&parse_steps(&steps)).is_none());
+    }
+
+    #[test]
+    fn movement_routines_turning_at_an_intersection() {
+        let image = "\
+....#######....
+###.#.....#....
+#.#.#...#.#....
+#####...#.#....
+..#.....#.#####
+..#######.....#
+..........###.#
+..........#.#.#
+........^######
+..........#.#..
+..........###..
+";
+        let (grid, robot) = scaffold::parse_grid(image).unwrap();
+        let robot = match robot {
+            RobotView::OnScaffold(robot) => robot,
+            RobotView::Tumbling(_) => panic!("robot is tumbling"),
+        };
+        let mut walks = Vec::new();
+        let found = find_scaffold_walk(&grid, robot, |steps| {
+            let steps = compress_steps(steps);
+            walks.push(encode_steps(&steps));
+            find_movement_routines(&steps).map(|routines| (steps, routines))
+        });
+        let (steps, routines) = found.unwrap();
+        check_routines(&steps, &routines);
+        // Going straight through every intersection gives a walk which can't be compressed
+        assert_eq!(
+            walks[0],
+            "R,4,L,2,L,2,L,4,L,2,L,2,R,2,L,4,L,4,R,4,L,6,L,3,R,4,R,2,R,2,R,4,L,6,L,3"
+        );
+        assert!(find_movement_routines(&parse_steps(&walks[0])).is_none());
+        assert_ne!(walks.last(), Some(&walks[0]));
+    }
+
+    #[test]
+    fn split_video_frames() {
+        let output = "\
+#^#
+...
+
+#>#
+...
+
+Continuous video feed?
+";
+        assert_eq!(
+            split_frames(output),
+            vec!["#^#\n...\n", "#>#\n...\n", "Continuous video feed?\n"]
+        );
+        assert_eq!(split_frames("\n\n#^#\n\n\n"), vec!["#^#\n"]);
+        assert!(split_frames("").is_empty());
+    }
+
+    #[test]
+    fn dump_video_frames() {
+        let dust = 1234;
+        let mut program = Vec::new();
+        for byte in "#^#\n\n#>#\n\n".bytes() {
+            program.extend_from_slice(&[104, byte as i128]);
+        }
+        program.extend_from_slice(&[104, dust, 99]);
+        let dump_dir = std::env::temp_dir().join(format!("day17-frames-{}", std::process::id()));
+        let feed = VideoFeed {
+            frame_duration: Duration::from_millis(0),
+            dump_dir: Some(dump_dir.clone()),
+        };
+        let mut robot = InstructRobot::new(&program);
+        assert_eq!(play_video_feed(&mut robot, &feed), dust);
+        let frame =
+            |index: usize| std::fs::read_to_string(dump_dir.join(format!("frame{:05}.txt", index)));
+        assert_eq!(frame(0).unwrap(), "#^#\n");
+        assert_eq!(frame(1).unwrap(), "#>#\n");
+        assert!(frame(2).is_err());
+        std::fs::remove_dir_all(&dump_dir).unwrap();
+    }
+}
@@ -1,5 +1,6 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::io::Read;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug)]
 enum ParamMode {
@@ -430,17 +431,89 @@ impl IntcodeComputer {
     }
 }
 
+const DEFAULT_SQUARE_SIZE: i128 = 100;
+const SLOPE_SAMPLE_ROW: i128 = 50;
+const MAX_BEAM_SLOPE: i128 = 4;
+const DEFAULT_RENDER_MARGIN: i128 = 10;
+// How far rounding can shift the width left for a square from the beam's true, linear width
+const MAX_EDGE_NOISE: i128 = 4;
+
 struct Solution {
     program: Vec<i128>,
+    drones: usize,
+    cache_hits: usize,
+    cache: HashMap<(i128, i128), bool>,
+}
+
+// FNV-1a, so that cache files stay valid across builds
+fn program_fingerprint(program: &[i128]) -> u64 {
+    let mut hash = 0xcbf29ce484222325u64;
+    for byte in program
+        .iter()
+        .flat_map(|value| value.to_le_bytes().to_vec())
+    {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
 }
 
 impl Solution {
     fn new(program: &[i128]) -> Self {
         Self {
             program: program.to_vec(),
+            drones: 0,
+            cache_hits: 0,
+            cache: HashMap::new(),
         }
     }
-    fn test_coord_is_pulled(&self, (x, y): (i128, i128)) -> bool {
+    // Cache files start with the program's fingerprint, followed by an "x,y,pulled" line per
+    // coordinate. A cache for a different program is ignored.
+    fn load_cache(&mut self, path: &Path) {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return,
+            Err(error) => panic!("failed to read {}: {}", path.display(), error),
+        };
+        let mut lines = contents.lines();
+        let fingerprint = format!("{:016x}", program_fingerprint(&self.program));
+        if lines.next() != Some(fingerprint.as_str()) {
+            eprintln!("ignoring cache for a different program: {}", path.display());
+            return;
+        }
+        for line in lines {
+            let fields = line
+                .split(',')
+                .map(|s| s.parse::<i128>().unwrap())
+                .collect::<Vec<_>>();
+            match fields.as_slice() {
+                &[x, y, pulled] => {
+                    self.cache.insert((x, y), pulled != 0);
+                }
+                _ => panic!("malformed cache line: {}", line),
+            }
+        }
+    }
+    fn save_cache(&self, path: &Path) {
+        let mut entries = self.cache.iter().collect::<Vec<_>>();
+        entries.sort();
+        let mut contents = format!("{:016x}\n", program_fingerprint(&self.program));
+        for (&(x, y), &pulled) in entries {
+            contents.push_str(&format!("{},{},{}\n", x, y, pulled as u8));
+        }
+        std::fs::write(path, contents).unwrap();
+    }
+    fn test_coord_is_pulled(&mut self, coord: (i128, i128)) -> bool {
+        if let Some(&pulled) = self.cache.get(&coord) {
+            self.cache_hits += 1;
+            return pulled;
+        }
+        let pulled = self.run_drone(coord);
+        self.cache.insert(coord, pulled);
+        pulled
+    }
+    fn run_drone(&mut self, (x, y): (i128, i128)) -> bool {
+        self.drones += 1;
         let mut computer = IntcodeComputer::new(&self.program);
         let mut input_buffer = IoBuffer::default();
         let mut output_buffer = IoBuffer::default();
@@ -456,7 +529,7 @@ impl Solution {
             }
         }
     }
-    fn run(&self) -> (i128, i128) {
+    fn scan(&mut self, size: i128) -> (i128, i128) {
         let mut start = None;
         for y in 1..50 {
             for x in 1..50 {
@@ -483,14 +556,284 @@ impl Solution {
                 diagonal_coord.0 -= 1;
                 diagonal_coord.1 += 1;
             }
-            if diagonal_count == 100 {
-                break (coord.0 - 99, coord.1);
+            if diagonal_count >= size {
+                break (coord.0 - (size - 1), coord.1);
             }
         }
     }
 }
 
+// The first and last pulled x in a row
+#[derive(Clone, Copy, Debug)]
+struct RowEdges {
+    left: i128,
+    right: i128,
+}
+
+struct BeamModel {
+    solution: Solution,
+    left_slope: f64,
+    right_slope: f64,
+    learned_row: i128,
+    rows: HashMap<i128, Option<RowEdges>>,
+}
+
+impl BeamModel {
+    fn learn(solution: Solution) -> Self {
+        let mut model = Self {
+            solution,
+            left_slope: 0.,
+            right_slope: 0.,
+            learned_row: 0,
+            rows: HashMap::new(),
+        };
+        let edges = model.scan_row(SLOPE_SAMPLE_ROW);
+        model.rows.insert(SLOPE_SAMPLE_ROW, edges);
+        model.learn_from(
+            SLOPE_SAMPLE_ROW,
+            edges.expect("beam does not reach the sample row"),
+        );
+        model
+    }
+    fn learn_from(&mut self, y: i128, edges: RowEdges) {
+        if y > self.learned_row {
+            self.left_slope = edges.left as f64 / y as f64;
+            self.right_slope = edges.right as f64 / y as f64;
+            self.learned_row = y;
+        }
+    }
+    fn scan_row(&mut self, y: i128) -> Option<RowEdges> {
+        let mut edges: Option<RowEdges> = None;
+        for x in 0..=(MAX_BEAM_SLOPE * (y + 1)) {
+            if self.solution.test_coord_is_pulled((x, y)) {
+                match edges.as_mut() {
+                    Some(edges) => edges.right = x,
+                    None => edges = Some(RowEdges { left: x, right: x }),
+                }
+            } else if edges.is_some() {
+                break;
+            }
+        }
+        edges
+    }
+    fn edges(&mut self, y: i128) -> Option<RowEdges> {
+        if let Some(&edges) = self.rows.get(&y) {
+            return edges;
+        }
+        let edges = self.find_edges(y);
+        self.rows.insert(y, edges);
+        if let Some(edges) = edges {
+            self.learn_from(y, edges);
+        }
+        edges
+    }
+    fn find_edges(&mut self, y: i128) -> Option<RowEdges> {
+        let predicted_left = (self.left_slope * y as f64).round() as i128;
+        let predicted_right = (self.right_slope * y as f64).round() as i128;
+        let middle = (predicted_left + predicted_right) / 2;
+        if !self.solution.test_coord_is_pulled((middle, y)) {
+            return self.scan_row(y);
+        }
+        Some(RowEdges {
+            left: self.search_edge(y, middle, predicted_left, -1),
+            right: self.search_edge(y, middle, predicted_right, 1),
+        })
+    }
+    // Finds the last pulled x stepping away from `inside` in the direction of `step`. Probing
+    // starts at `guess`, gallops outwards until the beam's edge is bracketed, then bisects.
+    fn search_edge(&mut self, y: i128, inside: i128, guess: i128, step: i128) -> i128 {
+        let guess = if (guess - inside) * step > 0 {
+            guess
+        } else {
+            inside + step
+        };
+        let (mut inside, mut outside) =
+            if guess >= 0 && self.solution.test_coord_is_pulled((guess, y)) {
+                let mut inside = guess;
+                let mut distance = 1;
+                loop {
+                    let x = (inside + step * distance).max(-1);
+                    if x < 0 || !self.solution.test_coord_is_pulled((x, y)) {
+                        break (inside, x);
+                    }
+                    inside = x;
+                    distance *= 2;
+                }
+            } else {
+                (inside, guess.max(-1))
+            };
+        while (outside - inside).abs() > 1 {
+            let x = (inside + outside) / 2;
+            if self.solution.test_coord_is_pulled((x, y)) {
+                inside = x;
+            } else {
+                outside = x;
+            }
+        }
+        inside
+    }
+    // How many columns the beam is short of fitting a square of the given size with its bottom
+    // row at `y`, which is zero or less if it fits
+    fn shortfall(&mut self, y: i128, size: i128) -> Option<i128> {
+        let top_y = y - (size - 1);
+        if top_y < 0 {
+            return None;
+        }
+        match (self.edges(y), self.edges(top_y)) {
+            (Some(bottom), Some(top)) => Some(bottom.left + (size - 1) - top.right),
+            _ => None,
+        }
+    }
+    fn fits(&mut self, y: i128, size: i128) -> bool {
+        self.shortfall(y, size)
+            .is_some_and(|shortfall| shortfall <= 0)
+    }
+    fn find_square(&mut self, size: i128) -> (i128, i128) {
+        let spread = (self.right_slope - self.left_slope).max(1. / SLOPE_SAMPLE_ROW as f64);
+        let estimate = (((size - 1) as f64 * (1. + self.right_slope)) / spread).ceil() as i128;
+        let estimate = estimate.max(size - 1);
+        let (mut lo, mut hi) = if self.fits(estimate, size) {
+            let mut hi = estimate;
+            let mut distance = 1;
+            loop {
+                let y = hi - distance;
+                if y < size - 1 || !self.fits(y, size) {
+                    break (y, hi);
+                }
+                hi = y;
+                distance *= 2;
+            }
+        } else {
+            let mut lo = estimate;
+            let mut distance = 1;
+            loop {
+                let y = lo + distance;
+                if self.fits(y, size) {
+                    break (lo, y);
+                }
+                lo = y;
+                distance *= 2;
+            }
+        };
+        while hi - lo > 1 {
+            let y = (lo + hi) / 2;
+            if self.fits(y, size) {
+                hi = y;
+            } else {
+                lo = y;
+            }
+        }
+        // The edges are whole columns so the shortfall wobbles rather than growing steadily, and
+        // a square might still fit a few rows above where the bisection ended up. Once it's
+        // beyond the noise, every row above is too narrow.
+        let mut y = hi - 1;
+        while let Some(shortfall) = self.shortfall(y, size) {
+            if shortfall > MAX_EDGE_NOISE {
+                break;
+            }
+            if shortfall <= 0 {
+                hi = y;
+            }
+            y -= 1;
+        }
+        let bottom = self.edges(hi).unwrap();
+        (bottom.left, hi - (size - 1))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Sample {
+    Still,
+    Pulled,
+    Square,
+}
+
+// Samples every cell around the square, which is highlighted
+fn sample_around_square(
+    solution: &mut Solution,
+    (square_x, square_y): (i128, i128),
+    size: i128,
+    margin: i128,
+) -> Vec<Vec<Sample>> {
+    let xs = (square_x - margin).max(0)..(square_x + size + margin);
+    let ys = (square_y - margin).max(0)..(square_y + size + margin);
+    ys.map(|y| {
+        xs.clone()
+            .map(|x| {
+                let in_square = (square_x..(square_x + size)).contains(&x)
+                    && (square_y..(square_y + size)).contains(&y);
+                match solution.test_coord_is_pulled((x, y)) {
+                    false => Sample::Still,
+                    true if in_square => Sample::Square,
+                    true => Sample::Pulled,
+                }
+            })
+            .collect()
+    })
+    .collect()
+}
+
+fn to_ascii(samples: &[Vec<Sample>]) -> String {
+    let mut string = String::new();
+    for row in samples {
+        for &sample in row {
+            string.push(match sample {
+                Sample::Still => '.',
+                Sample::Pulled => '#',
+                Sample::Square => 'O',
+            });
+        }
+        string.push('\n');
+    }
+    string
+}
+
+fn to_pgm(samples: &[Vec<Sample>]) -> String {
+    let width = samples.first().map_or(0, Vec::len);
+    let mut string = format!("P2\n{} {}\n255\n", width, samples.len());
+    for row in samples {
+        let levels = row
+            .iter()
+            .map(|&sample| match sample {
+                Sample::Still => "0",
+                Sample::Pulled => "128",
+                Sample::Square => "255",
+            })
+            .collect::<Vec<_>>();
+        string.push_str(&levels.join(" "));
+        string.push('\n');
+    }
+    string
+}
+
+fn render(path: &Path, samples: &[Vec<Sample>]) {
+    let contents = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("txt") => to_ascii(samples),
+        Some("pgm") => to_pgm(samples),
+        _ => panic!("unknown image format: {}", path.display()),
+    };
+    std::fs::write(path, contents).unwrap();
+}
+
 fn main() {
+    let mut size = DEFAULT_SQUARE_SIZE;
+    let mut use_scan = false;
+    let mut cache_path = None;
+    let mut render_path = None;
+    let mut margin = DEFAULT_RENDER_MARGIN;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().expect("expected a value after the option");
+        match arg.as_str() {
+            "--size" => size = value().parse().unwrap(),
+            "--scan" => use_scan = true,
+            "--cache" => cache_path = Some(PathBuf::from(value())),
+            "--render" => render_path = Some(PathBuf::from(value())),
+            "--margin" => margin = value().parse().unwrap(),
+            other => panic!("unexpected argument: {}", other),
+        }
+    }
+    assert!(size > 0, "square size must be positive");
     let mut input_string = String::new();
     std::io::stdin()
         .lock()
@@ -500,7 +843,80 @@ fn main() {
         .split(",")
         .map(|s| s.trim().parse::<i128>().unwrap())
         .collect::<Vec<_>>();
-    let solution = Solution::new(&program);
-    let (x, y) = solution.run();
+    let mut solution = Solution::new(&program);
+    if let Some(path) = cache_path.as_ref() {
+        solution.load_cache(path);
+    }
+    let (square, mut solution) = if use_scan {
+        let square = solution.scan(size);
+        (square, solution)
+    } else {
+        let mut model = BeamModel::learn(solution);
+        let square = model.find_square(size);
+        (square, model.solution)
+    };
+    let (drones, cache_hits) = (solution.drones, solution.cache_hits);
+    if let Some(path) = render_path {
+        let samples = sample_around_square(&mut solution, square, size, margin);
+        render(&path, &samples);
+    }
+    if let Some(path) = cache_path {
+        solution.save_cache(&path);
+    }
+    let (x, y) = square;
     println!("{}", x * 10000 + y);
+    eprintln!("drones: {} (cache hits: {})", drones, cache_hits);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn program() -> Vec<i128> {
+        include_str!("../input")
+            .trim()
+            .split(',')
+            .map(|s| s.parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn model_agrees_with_scan() {
+        // at 30 the bisection alone lands two rows below the answer
+        for &size in &[10, 20, 30] {
+            let mut model = BeamModel::learn(Solution::new(&program()));
+            let scanned = Solution::new(&program()).scan(size);
+            assert_eq!(model.find_square(size), scanned, "size {}", size);
+        }
+    }
+
+    #[test]
+    fn cache_round_trip() {
+        let path = std::env::temp_dir().join(format!("day19-cache-{}", std::process::id()));
+        let mut solution = Solution::new(&program());
+        solution.cache.insert((3, 4), true);
+        solution.cache.insert((5, 1), false);
+        solution.save_cache(&path);
+        let mut loaded = Solution::new(&program());
+        loaded.load_cache(&path);
+        assert_eq!(loaded.cache, solution.cache);
+        assert!(loaded.test_coord_is_pulled((3, 4)));
+        assert!(!loaded.test_coord_is_pulled((5, 1)));
+        assert_eq!((loaded.drones, loaded.cache_hits), (0, 2));
+        // a cache saved for another program is ignored
+        let mut other = Solution::new(&[99]);
+        other.load_cache(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(other.cache.is_empty());
+    }
+
+    #[test]
+    fn render_samples() {
+        let samples = vec![
+            vec![Sample::Still, Sample::Pulled, Sample::Still],
+            vec![Sample::Pulled, Sample::Square, Sample::Pulled],
+        ];
+        assert_eq!(to_ascii(&samples), ".#.\n#O#\n");
+        assert_eq!(to_pgm(&samples), "P2\n3 2\n255\n0 128 0\n128 255 128\n");
+    }
 }
@@ -451,6 +451,424 @@ impl IntcodeComputer {
     }
 }
 
+mod springscript {
+    pub const MAX_INSTRUCTIONS: usize = 15;
+
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub enum Register {
+        A,
+        B,
+        C,
+        D,
+        E,
+        F,
+        G,
+        H,
+        I,
+        T,
+        J,
+    }
+
+    const SENSORS: [Register; 9] = [
+        Register::A,
+        Register::B,
+        Register::C,
+        Register::D,
+        Register::E,
+        Register::F,
+        Register::G,
+        Register::H,
+        Register::I,
+    ];
+
+    impl Register {
+        fn from_str(s: &str) -> Option<Self> {
+            let register = match s {
+                "T" => Self::T,
+                "J" => Self::J,
+                _ => {
+                    let mut chars = s.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(ch @ 'A'..='I'), None) => SENSORS[(ch as u8 - b'A') as usize],
+                        _ => return None,
+                    }
+                }
+            };
+            Some(register)
+        }
+        /// How many tiles ahead of the droid a sensor register looks
+        pub fn sensor_distance(self) -> Option<usize> {
+            SENSORS
+                .iter()
+                .position(|&sensor| sensor == self)
+                .map(|index| index + 1)
+        }
+        pub fn is_writable(self) -> bool {
+            self == Self::T || self == Self::J
+        }
+    }
+
+    impl std::fmt::Display for Register {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "{:?}", self)
+        }
+    }
+
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub enum Op {
+        And,
+        Or,
+        Not,
+    }
+
+    impl std::fmt::Display for Op {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            match self {
+                Op::And => write!(f, "AND"),
+                Op::Or => write!(f, "OR"),
+                Op::Not => write!(f, "NOT"),
+            }
+        }
+    }
+
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub struct Instruction {
+        pub op: Op,
+        pub src: Register,
+        pub dst: Register,
+    }
+
+    impl std::fmt::Display for Instruction {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "{} {} {}", self.op, self.src, self.dst)
+        }
+    }
+
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub enum Mode {
+        Walk,
+        Run,
+    }
+
+    impl Mode {
+        pub fn num_sensors(self) -> usize {
+            match self {
+                Mode::Walk => 4,
+                Mode::Run => 9,
+            }
+        }
+    }
+
+    impl std::fmt::Display for Mode {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            match self {
+                Mode::Walk => write!(f, "WALK"),
+                Mode::Run => write!(f, "RUN"),
+            }
+        }
+    }
+
+    #[derive(Clone, PartialEq, Eq, Debug)]
+    pub enum ErrorKind {
+        UnknownOp(String),
+        UnknownRegister(String),
+        WrongArgCount(usize),
+        NotWritable(Register),
+        SensorOutOfRange(Register, Mode),
+        TooManyInstructions(usize),
+        MissingMode,
+    }
+
+    /// An error on a (1-based) line of the script
+    #[derive(Clone, PartialEq, Eq, Debug)]
+    pub struct ScriptError {
+        pub line: usize,
+        pub kind: ErrorKind,
+    }
+
+    impl std::fmt::Display for ScriptError {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "line {}: ", self.line)?;
+            match &self.kind {
+                ErrorKind::UnknownOp(op) => write!(f, "unknown instruction {:?}", op),
+                ErrorKind::UnknownRegister(register) => {
+                    write!(f, "unknown register {:?}", register)
+                }
+                ErrorKind::WrongArgCount(count) => {
+                    write!(f, "expected 2 registers but found {}", count)
+                }
+                ErrorKind::NotWritable(register) => {
+                    write!(f, "register {} is read-only", register)
+                }
+                ErrorKind::SensorOutOfRange(register, mode) => {
+                    write!(f, "register {} is not available in {} mode", register, mode)
+                }
+                ErrorKind::TooManyInstructions(count) => write!(
+                    f,
+                    "{} instructions is more than the limit of {}",
+                    count, MAX_INSTRUCTIONS
+                ),
+                ErrorKind::MissingMode => write!(f, "script must end with WALK or RUN"),
+            }
+        }
+    }
+
+    #[derive(Clone, PartialEq, Eq, Debug)]
+    pub struct Script {
+        pub instructions: Vec<Instruction>,
+        pub mode: Mode,
+    }
+
+    fn parse_instruction(line: &str, mode: Mode) -> Result<Instruction, ErrorKind> {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        let op = match words[0] {
+            "AND" => Op::And,
+            "OR" => Op::Or,
+            "NOT" => Op::Not,
+            other => return Err(ErrorKind::UnknownOp(other.to_string())),
+        };
+        if words.len() != 3 {
+            return Err(ErrorKind::WrongArgCount(words.len() - 1));
+        }
+        let register = |word: &str| {
+            let register = Register::from_str(word)
+                .ok_or_else(|| ErrorKind::UnknownRegister(word.to_string()))?;
+            match register.sensor_distance() {
+                Some(distance) if distance > mode.num_sensors() => {
+                    Err(ErrorKind::SensorOutOfRange(register, mode))
+                }
+                _ => Ok(register),
+            }
+        };
+        let src = register(words[1])?;
+        let dst = register(words[2])?;
+        if !dst.is_writable() {
+            return Err(ErrorKind::NotWritable(dst));
+        }
+        Ok(Instruction { op, src, dst })
+    }
+
+    impl Script {
+        /// Parses instruction lines (without the final WALK or RUN), skipping blank lines.
+        pub fn parse(lines: &[&str], mode: Mode) -> Result<Self, Vec<ScriptError>> {
+            let numbered_lines = lines
+                .iter()
+                .enumerate()
+                .filter(|(_, line)| !line.trim().is_empty())
+                .map(|(index, line)| (index + 1, line))
+                .collect::<Vec<_>>();
+            let mut instructions = Vec::new();
+            let mut errors = Vec::new();
+            for (count, &(line_number, line)) in numbered_lines.iter().enumerate() {
+                if count == MAX_INSTRUCTIONS {
+                    errors.push(ScriptError {
+                        line: line_number,
+                        kind: ErrorKind::TooManyInstructions(numbered_lines.len()),
+                    });
+                }
+                match parse_instruction(line, mode) {
+                    Ok(instruction) => instructions.push(instruction),
+                    Err(kind) => errors.push(ScriptError {
+                        line: line_number,
+                        kind,
+                    }),
+                }
+            }
+            if errors.is_empty() {
+                Ok(Self { instructions, mode })
+            } else {
+                Err(errors)
+            }
+        }
+        /// Parses a whole script as it would be sent to the droid, ending with WALK or RUN.
+        pub fn parse_source(source: &str) -> Result<Self, Vec<ScriptError>> {
+            let lines = source.lines().collect::<Vec<_>>();
+            let last = lines.iter().rposition(|line| !line.trim().is_empty());
+            let mode = last.and_then(|index| match lines[index].trim() {
+                "WALK" => Some(Mode::Walk),
+                "RUN" => Some(Mode::Run),
+                _ => None,
+            });
+            match (last, mode) {
+                (Some(index), Some(mode)) => Self::parse(&lines[..index], mode),
+                _ => Err(vec![ScriptError {
+                    line: last.map_or(1, |index| index + 1),
+                    kind: ErrorKind::MissingMode,
+                }]),
+            }
+        }
+        pub fn to_source(&self) -> String {
+            let mut source = String::new();
+            for instruction in &self.instructions {
+                source.push_str(&format!("{}\n", instruction));
+            }
+            source.push_str(&format!("{}\n", self.mode));
+            source
+        }
+        /// Whether the droid jumps given the ground seen by each sensor, nearest first
+        pub fn jumps(&self, ground: &[bool]) -> bool {
+            let (mut t, mut j) = (false, false);
+            for instruction in &self.instructions {
+                let src = match instruction.src {
+                    Register::T => t,
+                    Register::J => j,
+                    sensor => ground[sensor.sensor_distance().unwrap() - 1],
+                };
+                let dst = match instruction.dst {
+                    Register::T => &mut t,
+                    Register::J => &mut j,
+                    _ => unreachable!(),
+                };
+                *dst = match instruction.op {
+                    Op::And => src && *dst,
+                    Op::Or => src || *dst,
+                    Op::Not => !src,
+                };
+            }
+            j
+        }
+        /// Walks the droid across a hull such as `#####.#..########`, starting on the first tile.
+        /// Tiles past the end of the pattern are ground.
+        pub fn simulate(&self, hull: &str) -> Outcome {
+            let ground = hull.chars().map(|ch| ch == '#').collect::<Vec<_>>();
+            let is_ground = |x: usize| ground.get(x).copied().unwrap_or(true);
+            let mut x = 0;
+            let mut jumps = Vec::new();
+            while x < ground.len() {
+                let sensors = (1..=self.mode.num_sensors())
+                    .map(|distance| is_ground(x + distance))
+                    .collect::<Vec<_>>();
+                if self.jumps(&sensors) {
+                    jumps.push(x);
+                    x += 4;
+                } else {
+                    x += 1;
+                }
+                if !is_ground(x) {
+                    return Outcome::Fell { at: x, jumps };
+                }
+            }
+            Outcome::Survived { jumps }
+        }
+    }
+
+    /// Where the droid jumped from, and where it fell if it did
+    #[derive(Clone, PartialEq, Eq, Debug)]
+    pub enum Outcome {
+        Survived { jumps: Vec<usize> },
+        Fell { at: usize, jumps: Vec<usize> },
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        #[test]
+        fn round_trip() {
+            let source = "NOT A J\nOR T J\nAND D J\nWALK\n";
+            let script = Script::parse_source(source).unwrap();
+            assert_eq!(script.mode, Mode::Walk);
+            assert_eq!(script.instructions.len(), 3);
+            assert_eq!(script.to_source(), source);
+        }
+
+        #[test]
+        fn errors_by_line() {
+            let errors = Script::parse(
+                &["NOT A J", "", "XOR A J", "AND A B", "OR Q J", "NOT E J"],
+                Mode::Walk,
+            )
+            .unwrap_err();
+            let kinds = errors
+                .iter()
+                .map(|error| (error.line, error.kind.clone()))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                kinds,
+                vec![
+                    (3, ErrorKind::UnknownOp("XOR".to_string())),
+                    (4, ErrorKind::NotWritable(Register::B)),
+                    (5, ErrorKind::UnknownRegister("Q".to_string())),
+                    (6, ErrorKind::SensorOutOfRange(Register::E, Mode::Walk)),
+                ]
+            );
+            assert!(Script::parse(&["NOT E J"], Mode::Run).is_ok());
+            assert_eq!(
+                Script::parse_source("NOT A J\n").unwrap_err()[0].kind,
+                ErrorKind::MissingMode
+            );
+        }
+
+        #[test]
+        fn instruction_limit() {
+            let lines = vec!["NOT A J"; MAX_INSTRUCTIONS + 2];
+            assert!(Script::parse(&lines[..MAX_INSTRUCTIONS], Mode::Walk).is_ok());
+            let errors = Script::parse(&lines, Mode::Walk).unwrap_err();
+            assert_eq!(
+                errors,
+                vec![ScriptError {
+                    line: MAX_INSTRUCTIONS + 1,
+                    kind: ErrorKind::TooManyInstructions(MAX_INSTRUCTIONS + 2),
+                }]
+            );
+            // every instruction counts towards the limit, even ones with errors
+            let mut lines = lines.clone();
+            lines[0] = "XOR A J";
+            lines.insert(1, "");
+            let errors = Script::parse(&lines, Mode::Walk).unwrap_err();
+            let kinds = errors
+                .iter()
+                .map(|error| (error.line, error.kind.clone()))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                kinds,
+                vec![
+                    (1, ErrorKind::UnknownOp("XOR".to_string())),
+                    (
+                        MAX_INSTRUCTIONS + 2,
+                        ErrorKind::TooManyInstructions(MAX_INSTRUCTIONS + 2)
+                    ),
+                ]
+            );
+        }
+
+        #[test]
+        fn jump_over_holes() {
+            // Jump if there's a hole within reach and ground to land on
+            let script = Script::parse(
+                &[
+                    "NOT A J", "NOT B T", "OR T J", "NOT C T", "OR T J", "AND D J",
+                ],
+                Mode::Walk,
+            )
+            .unwrap();
+            assert_eq!(
+                script.simulate("#####.#..########"),
+                Outcome::Survived { jumps: vec![2, 6] }
+            );
+            assert_eq!(
+                script.simulate("#####.#.#...#####"),
+                Outcome::Fell {
+                    at: 7,
+                    jumps: vec![2]
+                }
+            );
+        }
+
+        #[test]
+        fn spring_script_survives_hull_patterns() {
+            let script = Script::parse(super::super::SPRING_SCRIPT, Mode::Run).unwrap();
+            for hull in super::super::HULL_PATTERNS {
+                assert!(
+                    matches!(script.simulate(hull), Outcome::Survived { .. }),
+                    "fell on {}",
+                    hull
+                );
+            }
+        }
+    }
+}
+
 struct Solution {
     input_buffer: IoBuffer,
     output_buffer: IoBuffer,
@@ -489,14 +907,11 @@ impl Solution {
             }
         }
     }
-    fn run(&mut self, spring_script: &[&str]) {
+    fn run(&mut self, script: &springscript::Script) {
         self.run_until_input();
         print!("{}", self.output_buffer.drain_ascii_string());
-        for s in spring_script {
-            self.input_buffer
-                .write_ascii_string(format!("{}\n", s).as_str(), Echo::On);
-        }
-        self.input_buffer.write_ascii_string("RUN\n", Echo::On);
+        self.input_buffer
+            .write_ascii_string(&script.to_source(), Echo::On);
         self.run_until_halt();
         print!("{}", self.output_buffer.drain_ascii_string());
         if let Some(damage) = self.output_buffer.read() {
@@ -505,7 +920,54 @@ impl Solution {
     }
 }
 
+fn parse_or_exit(
+    result: Result<springscript::Script, Vec<springscript::ScriptError>>,
+) -> springscript::Script {
+    result.unwrap_or_else(|errors| {
+        for error in errors {
+            eprintln!("{}", error);
+        }
+        std::process::exit(1);
+    })
+}
+
+fn check(script: &springscript::Script) {
+    for hull in HULL_PATTERNS {
+        match script.simulate(hull) {
+            springscript::Outcome::Survived { jumps } => {
+                println!("{} survived, jumping from {:?}", hull, jumps)
+            }
+            springscript::Outcome::Fell { at, jumps } => {
+                println!("{} fell at {}, jumping from {:?}", hull, at, jumps)
+            }
+        }
+    }
+}
+
+// Usage: [run | check] [SCRIPT]. Without a script file the built-in one is used.
 fn main() {
+    let mut check_only = false;
+    let mut script_path = None;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "run" => check_only = false,
+            "check" => check_only = true,
+            _ if script_path.is_none() => script_path = Some(arg),
+            other => panic!("unexpected argument: {}", other),
+        }
+    }
+    let script = match script_path {
+        Some(path) => parse_or_exit(springscript::Script::parse_source(
+            &std::fs::read_to_string(path).unwrap(),
+        )),
+        None => parse_or_exit(springscript::Script::parse(
+            SPRING_SCRIPT,
+            springscript::Mode::Run,
+        )),
+    };
+    if check_only {
+        return check(&script);
+    }
     let mut input_string = String::new();
     std::io::stdin()
         .lock()
@@ -516,7 +978,7 @@ fn main() {
         .map(|s| s.trim().parse::<i128>().unwrap())
         .collect::<Vec<_>>();
     let mut solution = Solution::new(&program);
-    solution.run(SPRING_SCRIPT);
+    solution.run(&script);
 }
 
 #[rustfmt::skip]
@@ -533,10 +995,13 @@ const SPRING_SCRIPT: &[&str] = &[
     "AND D J",
 ];
 
-// #####.###########
-// #####...#########
-// #####.#..########
-// #####.##.########
-// #####.##.##..####
-// #####.#.#...#####
-// #####.##.#.#..###
+#[rustfmt::skip]
+const HULL_PATTERNS: &[&str] = &[
+    "#####.###########",
+    "#####...#########",
+    "#####.#..########",
+    "#####.##.########",
+    "#####.##.##..####",
+    "#####.#.#...#####",
+    "#####.##.#.#..###",
+];
@@ -1,6 +1,10 @@
+use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::collections::VecDeque;
-use std::io::Read;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::Command;
 
 #[derive(Debug)]
 enum ParamMode {
@@ -111,11 +115,12 @@ impl Opcode {
     }
 }
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 struct IoBuffer {
     values: VecDeque<i128>,
 }
 
+#[derive(Clone, Copy)]
 enum Echo {
     On,
     Off,
@@ -169,7 +174,7 @@ enum Status {
     Halt,
 }
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 struct State {
     ip: usize,
     relative_base: i128,
@@ -413,6 +418,7 @@ impl Instruction {
     }
 }
 
+#[derive(Clone)]
 struct IntcodeComputer {
     memory: Vec<i128>,
     state: State,
@@ -452,22 +458,23 @@ impl IntcodeComputer {
             Status::WroteOutput => Some(StopStatus::WroteOutput),
         }
     }
-    fn run(&mut self, input_buffer: &mut IoBuffer, output_buffer: &mut IoBuffer) -> StopStatus {
-        loop {
-            if let Some(stop_status) = self.step(input_buffer, output_buffer) {
-                return stop_status;
-            }
-        }
-    }
 }
 
+#[derive(Clone)]
 struct Computer {
     input_buffer: IoBuffer,
     output_buffer: IoBuffer,
     computer: IntcodeComputer,
+    echo: Echo,
+}
+
+enum Response {
+    Prompt(String),
+    Halt(String),
+    Runaway,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 enum Direction {
     North,
     East,
@@ -476,13 +483,13 @@ enum Direction {
 }
 
 impl Direction {
-    fn parse(s: &str) -> Self {
+    fn parse(s: &str) -> Option<Self> {
         match s {
-            "north" => Self::North,
-            "east" => Self::East,
-            "south" => Self::South,
-            "west" => Self::West,
-            other => panic!("not direction: {}", other),
+            "north" => Some(Self::North),
+            "east" => Some(Self::East),
+            "south" => Some(Self::South),
+            "west" => Some(Self::West),
+            _ => None,
         }
     }
     fn to_str(&self) -> &'static str {
@@ -501,9 +508,17 @@ impl Direction {
             Self::West => Self::East,
         }
     }
+    fn offset(self) -> (i32, i32) {
+        match self {
+            Self::North => (0, -1),
+            Self::East => (1, 0),
+            Self::South => (0, 1),
+            Self::West => (-1, 0),
+        }
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct Room {
     exits: Vec<Direction>,
     name: String,
@@ -511,64 +526,453 @@ struct Room {
     items: Vec<String>,
 }
 
-impl Room {
-    fn parse(s: &str) -> Self {
-        //println!("parsing #####\n{}\n#####", s);
-        let trimmed = s.trim();
-        let lines = trimmed.lines().collect::<Vec<_>>();
-        let name = lines[0].split(" ").nth(1).unwrap();
-        let description = lines[1];
-        assert_eq!(lines[3], "Doors here lead:");
-        let mut exits = Vec::new();
-        for &line in &lines[4..] {
-            if line.starts_with("- ") {
-                exits.push(Direction::parse(
-                    &line.split(' ').skip(1).collect::<Vec<_>>().join(" "),
-                ))
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Alert {
+    Heavy,
+    Light,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Refusal {
+    NoDoor,
+    ItemNotHere,
+    ItemNotCarried,
+    UnrecognizedCommand,
+    Stuck,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum AdventureEvent {
+    RoomEntered(Room),
+    ItemTaken(String),
+    ItemDropped(String),
+    Inventory(Vec<String>),
+    Alert(Alert),
+    Ejected(Room),
+    Refused(Refusal),
+    GameOver(String),
+    Password(u64),
+    Prompt,
+}
+
+#[derive(Debug)]
+struct ParseError {
+    text: String,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "unrecognized text:\n{}", self.text)
+    }
+}
+
+const HEAVY_ALERT: &str = "Alert! Droids on this ship are heavier than the detected value!";
+const LIGHT_ALERT: &str = "Alert! Droids on this ship are lighter than the detected value!";
+const ROBOTIC_VOICE: &str = "A loud, robotic voice says";
+const GAME_OVER_MESSAGES: &[&str] = &[
+    "You're launched into space! Bye!",
+    "The molten lava is way too hot! You melt!",
+    "It is suddenly completely dark! You are eaten by a Grue!",
+];
+
+fn paragraphs(text: &str) -> Vec<Vec<&str>> {
+    let mut paragraphs = vec![Vec::new()];
+    for line in text.lines().map(str::trim_end) {
+        if line.is_empty() {
+            if !paragraphs.last().unwrap().is_empty() {
+                paragraphs.push(Vec::new());
+            }
+        } else {
+            paragraphs.last_mut().unwrap().push(line);
+        }
+    }
+    paragraphs.retain(|paragraph| !paragraph.is_empty());
+    paragraphs
+}
+
+fn parse_list<'a>(paragraph: &[&'a str]) -> Result<Vec<&'a str>, ParseError> {
+    paragraph[1..]
+        .iter()
+        .map(|line| {
+            line.strip_prefix("- ").ok_or_else(|| ParseError {
+                text: paragraph.join("\n"),
+            })
+        })
+        .collect()
+}
+
+fn parse_password(text: &str) -> Option<u64> {
+    const PREFIX: &str = "typing ";
+    let start = text.find(PREFIX)? + PREFIX.len();
+    text[start..].split_whitespace().next()?.parse().ok()
+}
+
+fn parse_adventure(text: &str) -> Result<Vec<AdventureEvent>, ParseError> {
+    let mut events = Vec::new();
+    let mut paragraphs = paragraphs(text).into_iter().peekable();
+    let mut ejected = false;
+    while let Some(paragraph) = paragraphs.next() {
+        let text = paragraph.join("\n");
+        let unrecognized = || ParseError { text: text.clone() };
+        let first = paragraph[0];
+        let event = if let Some(name) = first
+            .strip_prefix("== ")
+            .and_then(|s| s.strip_suffix(" =="))
+        {
+            let mut room = Room {
+                exits: Vec::new(),
+                name: name.to_string(),
+                description: paragraph[1..].join("\n"),
+                items: Vec::new(),
+            };
+            while let Some(list) = paragraphs.peek() {
+                match list[0] {
+                    "Doors here lead:" => {
+                        room.exits = parse_list(list)?
+                            .into_iter()
+                            .map(|door| Direction::parse(door).ok_or_else(unrecognized))
+                            .collect::<Result<_, _>>()?
+                    }
+                    "Items here:" => {
+                        room.items = parse_list(list)?.into_iter().map(str::to_string).collect()
+                    }
+                    _ => break,
+                }
+                paragraphs.next();
+            }
+            if ejected {
+                ejected = false;
+                AdventureEvent::Ejected(room)
             } else {
-                break;
+                AdventureEvent::RoomEntered(room)
+            }
+        } else if first == "Command?" {
+            AdventureEvent::Prompt
+        } else if let Some(item) = first
+            .strip_prefix("You take the ")
+            .and_then(|s| s.strip_suffix('.'))
+        {
+            AdventureEvent::ItemTaken(item.to_string())
+        } else if let Some(item) = first
+            .strip_prefix("You drop the ")
+            .and_then(|s| s.strip_suffix('.'))
+        {
+            AdventureEvent::ItemDropped(item.to_string())
+        } else if first == "Items in your inventory:" {
+            AdventureEvent::Inventory(
+                parse_list(&paragraph)?
+                    .into_iter()
+                    .map(str::to_string)
+                    .collect(),
+            )
+        } else if first == "You aren't carrying any items." {
+            AdventureEvent::Inventory(Vec::new())
+        } else if first.starts_with(ROBOTIC_VOICE) && text.contains(HEAVY_ALERT) {
+            ejected = true;
+            AdventureEvent::Alert(Alert::Heavy)
+        } else if first.starts_with(ROBOTIC_VOICE) && text.contains(LIGHT_ALERT) {
+            ejected = true;
+            AdventureEvent::Alert(Alert::Light)
+        } else if first.starts_with(ROBOTIC_VOICE) {
+            AdventureEvent::Password(parse_password(&text).ok_or_else(unrecognized)?)
+        } else if GAME_OVER_MESSAGES.contains(&text.as_str()) {
+            AdventureEvent::GameOver(text.clone())
+        } else {
+            AdventureEvent::Refused(match first {
+                "You can't go that way." => Refusal::NoDoor,
+                "You don't see that item here." => Refusal::ItemNotHere,
+                "You don't have that item." => Refusal::ItemNotCarried,
+                "Unrecognized command." => Refusal::UnrecognizedCommand,
+                _ if first.ends_with("You can't move!!") => Refusal::Stuck,
+                _ => return Err(unrecognized()),
+            })
+        };
+        events.push(event);
+    }
+    Ok(events)
+}
+
+#[derive(Debug)]
+enum ExploreError {
+    Parse(ParseError),
+    Halted(String),
+    NoResponse,
+    NoRoom,
+    NoPressureFloor,
+}
+
+impl std::fmt::Display for ExploreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Parse(error) => write!(f, "{}", error),
+            Self::Halted(text) => write!(f, "unexpected halt: {}", text),
+            Self::NoResponse => write!(f, "no prompt after {} steps", MAX_STEPS_PER_COMMAND),
+            Self::NoRoom => write!(f, "the droid is not in a room"),
+            Self::NoPressureFloor => write!(f, "no pressure-sensitive floor was found"),
+        }
+    }
+}
+
+impl From<ParseError> for ExploreError {
+    fn from(error: ParseError) -> Self {
+        Self::Parse(error)
+    }
+}
+
+fn adventure_events(text: &str) -> Vec<AdventureEvent> {
+    parse_adventure(text).unwrap_or_else(|error| panic!("{}", error))
+}
+
+fn current_room(events: &[AdventureEvent]) -> Option<&Room> {
+    events.iter().rev().find_map(|event| match event {
+        AdventureEvent::RoomEntered(room) | AdventureEvent::Ejected(room) => Some(room),
+        _ => None,
+    })
+}
+
+type Path = Vec<(String, Direction)>;
+
+#[derive(Clone, Debug, Default)]
+struct ShipMap {
+    start: String,
+    rooms: BTreeMap<String, Room>,
+    doors: BTreeMap<(String, Direction), String>,
+}
+
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl ShipMap {
+    fn add_room(&mut self, room: &Room) {
+        if self.rooms.is_empty() {
+            self.start = room.name.clone();
+        }
+        self.rooms.insert(room.name.clone(), room.clone());
+    }
+    fn connect(&mut self, from: &str, direction: Direction, to: &str) {
+        self.doors
+            .insert((from.to_string(), direction), to.to_string());
+        self.doors
+            .insert((to.to_string(), direction.opposite()), from.to_string());
+    }
+    fn merge(&mut self, other: ShipMap) {
+        if self.rooms.is_empty() {
+            self.start = other.start;
+        }
+        self.rooms.extend(other.rooms);
+        self.doors.extend(other.doors);
+    }
+    fn doors_from<'a>(&'a self, name: &str) -> impl 'a + Iterator<Item = (Direction, &'a String)> {
+        self.doors
+            .range((name.to_string(), Direction::North)..=(name.to_string(), Direction::West))
+            .map(|((_, direction), to)| (*direction, to))
+    }
+    fn path(&self, from: &str, to: &str) -> Option<Vec<Direction>> {
+        let mut previous: HashMap<&String, (&String, Direction)> = HashMap::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(self.rooms.get_key_value(from)?.0);
+        while let Some(name) = queue.pop_front() {
+            if name == to {
+                let mut path = Vec::new();
+                let mut current = name;
+                while let Some(&(before, direction)) = previous.get(current) {
+                    path.push(direction);
+                    current = before;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for (direction, next) in self.doors_from(name) {
+                if next != from && !previous.contains_key(next) {
+                    previous.insert(next, (name, direction));
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+    fn to_dot(&self) -> String {
+        let mut dot = String::from("graph ship {\n    node [shape=box];\n");
+        for room in self.rooms.values() {
+            let mut label = dot_escape(&room.name);
+            if room.name == self.start {
+                label.push_str("\\n(start)");
+            }
+            for item in &room.items {
+                label.push_str(&format!("\\n- {}", dot_escape(item)));
+            }
+            dot.push_str(&format!(
+                "    \"{}\" [label=\"{}\", tooltip=\"{}\"];\n",
+                dot_escape(&room.name),
+                label,
+                dot_escape(&room.description)
+            ));
+        }
+        for ((from, direction), to) in &self.doors {
+            if *direction == Direction::North || *direction == Direction::East {
+                dot.push_str(&format!(
+                    "    \"{}\" -- \"{}\" [taillabel=\"{}\", headlabel=\"{}\"];\n",
+                    dot_escape(from),
+                    dot_escape(to),
+                    direction.to_str(),
+                    direction.opposite().to_str()
+                ));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+    fn layout(&self) -> Vec<(String, (i32, i32))> {
+        let mut placed = vec![(self.start.clone(), (0, 0))];
+        let mut by_position = HashMap::new();
+        by_position.insert((0, 0), self.start.clone());
+        let mut queue = VecDeque::new();
+        queue.push_back((self.start.clone(), (0, 0)));
+        while let Some((name, (x, y))) = queue.pop_front() {
+            for (direction, to) in self.doors_from(&name) {
+                if placed.iter().any(|(placed_name, _)| placed_name == to) {
+                    continue;
+                }
+                let (dx, dy) = direction.offset();
+                let position = (1..)
+                    .map(|distance| (x + dx * distance, y + dy * distance))
+                    .find(|position| !by_position.contains_key(position))
+                    .unwrap();
+                by_position.insert(position, to.clone());
+                placed.push((to.clone(), position));
+                queue.push_back((to.clone(), position));
             }
         }
-        let mut items = Vec::new();
-        let item_head_offset = 4 + exits.len() + 1;
-        if lines[item_head_offset] == "Items here:" {
-            for &line in &lines[item_head_offset + 1..] {
-                if line.starts_with("- ") {
-                    items.push(line.split(' ').skip(1).collect::<Vec<_>>().join(" "));
+        placed
+    }
+    fn to_ascii(&self) -> String {
+        const CELL_WIDTH: usize = 7;
+        const BOX_WIDTH: usize = 4;
+        let placed = self.layout();
+        let min_x = placed.iter().map(|(_, (x, _))| *x).min().unwrap();
+        let min_y = placed.iter().map(|(_, (_, y))| *y).min().unwrap();
+        let max_x = placed.iter().map(|(_, (x, _))| *x).max().unwrap();
+        let max_y = placed.iter().map(|(_, (_, y))| *y).max().unwrap();
+        let width = (max_x - min_x + 1) as usize * CELL_WIDTH;
+        let height = (max_y - min_y) as usize * 2 + 1;
+        let mut grid = vec![vec![' '; width]; height];
+        let position_of = |name: &str| {
+            placed
+                .iter()
+                .find(|(placed_name, _)| placed_name == name)
+                .map(|&(_, (x, y))| ((x - min_x) as usize * CELL_WIDTH, (y - min_y) as usize * 2))
+        };
+        for (index, (name, _)) in placed.iter().enumerate() {
+            let (column, row) = position_of(name).unwrap();
+            for (i, c) in format!("[{:>2}]", index).chars().enumerate() {
+                grid[row][column + i] = c;
+            }
+        }
+        let mut hidden_doors = Vec::new();
+        for (name, _) in &placed {
+            let (column, row) = position_of(name).unwrap();
+            for &direction in &[Direction::East, Direction::South] {
+                let neighbour = match self.doors.get(&(name.clone(), direction)) {
+                    Some(neighbour) => neighbour,
+                    None => continue,
+                };
+                let (c, r) = position_of(neighbour).unwrap();
+                let cells = match direction {
+                    Direction::East if r == row => (column + BOX_WIDTH..c)
+                        .map(|c| (row, c))
+                        .collect::<Vec<_>>(),
+                    Direction::South if c == column => {
+                        (row + 1..r).map(|r| (r, column + 2)).collect::<Vec<_>>()
+                    }
+                    _ => Vec::new(),
+                };
+                let clear = !cells.is_empty()
+                    && cells
+                        .iter()
+                        .all(|&(r, c)| grid[r][c] == ' ' || grid[r][c] == '-' || grid[r][c] == '|');
+                if !clear {
+                    hidden_doors.push((name.clone(), direction, neighbour.clone()));
+                    continue;
+                }
+                let line = if direction == Direction::East {
+                    '-'
                 } else {
-                    break;
+                    '|'
+                };
+                for (r, c) in cells {
+                    grid[r][c] = if grid[r][c] == ' ' || grid[r][c] == line {
+                        line
+                    } else {
+                        '+'
+                    };
                 }
             }
         }
-        Self {
-            exits,
-            name: name.to_string(),
-            description: description.to_string(),
-            items,
+        let mut ascii = String::new();
+        for row in grid {
+            ascii.push_str(row.into_iter().collect::<String>().trim_end());
+            ascii.push('\n');
+        }
+        ascii.push('\n');
+        for (index, (name, _)) in placed.iter().enumerate() {
+            let room = &self.rooms[name];
+            ascii.push_str(&format!("{:>2}: {}", index, name));
+            if *name == self.start {
+                ascii.push_str(" (start)");
+            }
+            if !room.items.is_empty() {
+                ascii.push_str(&format!(" [{}]", room.items.join(", ")));
+            }
+            ascii.push('\n');
+        }
+        for (from, direction, to) in hidden_doors {
+            ascii.push_str(&format!(
+                "not drawn: {} {} to {}\n",
+                from,
+                direction.to_str(),
+                to
+            ));
         }
+        ascii
     }
 }
 
-type Path = Vec<(String, Direction)>;
-
 #[derive(Debug)]
 struct Ship {
     path_from_hull_to_security: Path,
     paths_to_each_item: HashMap<String, Path>,
     pressure_room_direction: Direction,
+    map: ShipMap,
 }
 
+impl Ship {
+    fn items(&self) -> Vec<String> {
+        let mut items = self.paths_to_each_item.keys().cloned().collect::<Vec<_>>();
+        items.sort();
+        items
+    }
+}
+
+const MAX_STEPS_PER_COMMAND: usize = 10_000_000;
+const MAX_OUTPUT_PER_COMMAND: usize = 100_000;
+
 impl Computer {
     fn new(program: &[i128]) -> Self {
         Self {
             input_buffer: IoBuffer::default(),
             output_buffer: IoBuffer::default(),
             computer: IntcodeComputer::new(program),
+            echo: Echo::On,
         }
     }
-    fn explore(&mut self) -> Ship {
-        let prompt = self.run_until_prompt();
-        let room = Room::parse(&prompt);
+    fn explore(&mut self) -> Result<Ship, ExploreError> {
+        let events = parse_adventure(&self.prompt()?)?;
+        self.explore_from(current_room(&events).ok_or(ExploreError::NoRoom)?)
+    }
+    fn explore_from(&mut self, room: &Room) -> Result<Ship, ExploreError> {
+        let mut map = ShipMap::default();
+        map.add_room(room);
         let mut path_from_start_to_current: Vec<(String, Direction)> = Vec::new();
         let mut exits_to_explore = Vec::new();
         exits_to_explore.extend(room.exits.iter().map(|&exit| (room.name.clone(), exit)));
@@ -584,32 +988,29 @@ impl Computer {
                     &format!("{}\n", direction_to_get_here.opposite().to_str()),
                     Echo::Off,
                 );
-                let prompt = self.run_until_prompt();
-                let _room = Room::parse(&prompt);
+                self.prompt()?;
                 path_from_start_to_current.pop();
             }
             self.input_buffer
                 .write_ascii_string(&format!("{}\n", exit_to_explore.to_str()), Echo::Off);
-            let prompt = self.run_until_prompt();
-            let room = Room::parse(&prompt);
-            path_from_start_to_current.push((room.name.clone(), exit_to_explore));
-            if room.name == "Security" {
-                let mut path_to_security = path_from_start_to_current.clone();
-                assert_eq!(room.exits.len(), 2);
-                let &exit = room
-                    .exits
-                    .iter()
-                    .filter(|&&exit| exit != exit_to_explore.opposite())
-                    .next()
-                    .unwrap();
-                maybe_path_to_security = Some(path_to_security);
-                maybe_pressure_room_direction = Some(exit);
+            let events = parse_adventure(&self.prompt()?)?;
+            if let Some(AdventureEvent::RoomEntered(room)) = events.first() {
+                map.add_room(room);
+                map.connect(&room_name_to_explore_from, exit_to_explore, &room.name);
+            }
+            if events
+                .iter()
+                .any(|event| matches!(event, AdventureEvent::Alert(_)))
+            {
+                maybe_path_to_security = Some(path_from_start_to_current.clone());
+                maybe_pressure_room_direction = Some(exit_to_explore);
+                continue;
             }
+            let room = current_room(&events).ok_or(ExploreError::NoRoom)?;
+            path_from_start_to_current.push((room.name.clone(), exit_to_explore));
             exits_to_explore.extend(room.exits.iter().filter_map(|&exit| {
                 if exit == exit_to_explore.opposite() {
                     None
-                } else if room.name == "Security" && exit == Direction::North {
-                    None
                 } else {
                     Some((room.name.clone(), exit))
                 }
@@ -623,59 +1024,85 @@ impl Computer {
                 &format!("{}\n", direction_to_get_here.opposite().to_str()),
                 Echo::Off,
             );
-            let prompt = self.run_until_prompt();
-            let room = Room::parse(&prompt);
+            self.prompt()?;
         }
-        Ship {
-            path_from_hull_to_security: maybe_path_to_security.unwrap(),
-            paths_to_each_item: item_name_to_path,
-            pressure_room_direction: maybe_pressure_room_direction.unwrap(),
+        match (maybe_path_to_security, maybe_pressure_room_direction) {
+            (Some(path_from_hull_to_security), Some(pressure_room_direction)) => Ok(Ship {
+                path_from_hull_to_security,
+                paths_to_each_item: item_name_to_path,
+                pressure_room_direction,
+                map,
+            }),
+            _ => Err(ExploreError::NoPressureFloor),
         }
     }
-    fn run_until_prompt(&mut self) -> String {
-        loop {
+    fn run_until_response(&mut self) -> Response {
+        for _ in 0..MAX_STEPS_PER_COMMAND {
+            if self.output_buffer.len() > MAX_OUTPUT_PER_COMMAND {
+                break;
+            }
             match self
                 .computer
-                .run(&mut self.input_buffer, &mut self.output_buffer)
+                .step(&mut self.input_buffer, &mut self.output_buffer)
             {
-                StopStatus::Halt => panic!(
-                    "unexpected halt: {}",
-                    self.output_buffer.drain_ascii_string()
-                ),
-                StopStatus::WroteOutput => (),
-                StopStatus::WaitForInput => return self.output_buffer.drain_ascii_string(),
+                None | Some(StopStatus::WroteOutput) => (),
+                Some(StopStatus::Halt) => {
+                    return Response::Halt(self.output_buffer.drain_ascii_string())
+                }
+                Some(StopStatus::WaitForInput) => {
+                    return Response::Prompt(self.output_buffer.drain_ascii_string())
+                }
             }
         }
+        Response::Runaway
+    }
+    fn prompt(&mut self) -> Result<String, ExploreError> {
+        match self.run_until_response() {
+            Response::Prompt(prompt) => Ok(prompt),
+            Response::Halt(text) => Err(ExploreError::Halted(text)),
+            Response::Runaway => Err(ExploreError::NoResponse),
+        }
+    }
+    fn run_until_prompt(&mut self) -> String {
+        self.prompt().unwrap_or_else(|error| panic!("{}", error))
+    }
+    fn send(&mut self, command: &str) -> Response {
+        self.input_buffer
+            .write_ascii_string(&format!("{}\n", command), self.echo);
+        let response = self.run_until_response();
+        if let Echo::On = self.echo {
+            match &response {
+                Response::Prompt(text) | Response::Halt(text) => println!("{}", text),
+                Response::Runaway => println!("(no response)"),
+            }
+        }
+        response
+    }
+    fn command(&mut self, command: &str) -> String {
+        match self.send(command) {
+            Response::Prompt(prompt) => prompt,
+            Response::Halt(text) => panic!("unexpected halt after {}: {}", command, text),
+            Response::Runaway => panic!("no prompt after {}", command),
+        }
     }
     fn follow_path(&mut self, path: &Path) {
         for (room_name, direction) in path {
-            self.input_buffer
-                .write_ascii_string(&format!("{}\n", direction.to_str()), Echo::On);
-            let prompt = self.run_until_prompt();
-            let room = Room::parse(&prompt);
-            assert_eq!(room_name, &room.name);
-            println!("{}", prompt);
+            let events = adventure_events(&self.command(direction.to_str()));
+            assert_eq!(
+                Some(room_name),
+                current_room(&events).map(|room| &room.name)
+            );
         }
     }
     fn take(&mut self, item: &str) {
-        self.input_buffer
-            .write_ascii_string(&format!("take {}\n", item), Echo::On);
-        let prompt = self.run_until_prompt();
-        println!("{}", prompt);
+        self.command(&format!("take {}", item));
     }
     fn drop(&mut self, item: &str) {
-        self.input_buffer
-            .write_ascii_string(&format!("drop {}\n", item), Echo::On);
-        let prompt = self.run_until_prompt();
-        println!("{}", prompt);
+        self.command(&format!("drop {}", item));
     }
     fn follow_path_rev(&mut self, path: &Path) {
         for (_room_name, direction) in path.iter().rev() {
-            self.input_buffer
-                .write_ascii_string(&format!("{}\n", direction.opposite().to_str()), Echo::On);
-            let prompt = self.run_until_prompt();
-            let _room = Room::parse(&prompt);
-            println!("{}", prompt);
+            self.command(direction.opposite().to_str());
         }
     }
     fn collect_item(&mut self, ship: &Ship, item: &str) {
@@ -687,85 +1114,601 @@ impl Computer {
     fn go_to_security_checkpoint(&mut self, ship: &Ship) {
         self.follow_path(&ship.path_from_hull_to_security);
     }
-    fn inv(&mut self) -> String {
-        self.input_buffer.write_ascii_string("inv\n", Echo::On);
-        self.run_until_prompt()
+    fn item_is_safe(&self, ship: &Ship, item: &str) -> bool {
+        let path = ship.paths_to_each_item.get(item).unwrap();
+        let retreat = match path.last() {
+            Some(&(_, direction)) => direction.opposite(),
+            None => ship.path_from_hull_to_security[0].1,
+        };
+        let directions = path
+            .iter()
+            .map(|&(_, direction)| direction)
+            .collect::<Vec<_>>();
+        self.item_is_safe_along(&directions, item, retreat)
+    }
+    fn item_is_safe_along(&self, path: &[Direction], item: &str, retreat: Direction) -> bool {
+        let mut probe = self.clone();
+        probe.echo = Echo::Off;
+        for direction in path {
+            if let Response::Prompt(_) = probe.send(direction.to_str()) {
+                continue;
+            }
+            return false;
+        }
+        let taken = AdventureEvent::ItemTaken(item.to_string());
+        match probe.send(&format!("take {}", item)) {
+            Response::Prompt(text) if matches!(parse_adventure(&text), Ok(events) if events.contains(&taken)) =>
+                {}
+            _ => return false,
+        }
+        match probe.send(retreat.to_str()) {
+            Response::Prompt(text) => {
+                matches!(parse_adventure(&text), Ok(events) if current_room(&events).is_some())
+            }
+            _ => false,
+        }
     }
-    fn collect_all_items(&mut self, ship: &Ship) {
-        for item in ALL_ITEMS {
+    fn collect_safe_items(&mut self, ship: &Ship) -> Vec<String> {
+        let items = ship
+            .items()
+            .into_iter()
+            .filter(|item| self.item_is_safe(ship, item))
+            .collect::<Vec<_>>();
+        for item in &items {
             self.collect_item(ship, item);
         }
+        items
     }
-    fn drop_all_items_in_security_room(&mut self, ship: &Ship) {
-        self.follow_path(&ship.path_from_hull_to_security);
-        for item in ALL_ITEMS {
+    fn drop_all_items_in_security_room(&mut self, ship: &Ship, items: &[String]) {
+        self.go_to_security_checkpoint(ship);
+        for item in items {
             self.drop(item);
         }
     }
     fn try_to_pass_pressure_room(&mut self, ship: &Ship) -> PressureRoom {
-        self.input_buffer.write_ascii_string(
-            &format!("{}\n", ship.pressure_room_direction.to_str()),
-            Echo::On,
-        );
-        let prompt = self.run_until_prompt();
-        const HEAVY_MESSAGE: &str =
-            "Alert! Droids on this ship are heavier than the detected value!";
-        const LIGHT_MESSAGE: &str =
-            "Alert! Droids on this ship are lighter than the detected value!";
-        if prompt.contains(HEAVY_MESSAGE) {
-            PressureRoom::Heavy
-        } else if prompt.contains(LIGHT_MESSAGE) {
-            PressureRoom::Light
-        } else {
-            PressureRoom::Correct
+        let text = match self.send(ship.pressure_room_direction.to_str()) {
+            Response::Prompt(text) | Response::Halt(text) => text,
+            Response::Runaway => panic!("no response from pressure room"),
+        };
+        adventure_events(&text)
+            .into_iter()
+            .find_map(|event| match event {
+                AdventureEvent::Alert(Alert::Heavy) => Some(PressureRoom::Heavy),
+                AdventureEvent::Alert(Alert::Light) => Some(PressureRoom::Light),
+                AdventureEvent::Password(password) => Some(PressureRoom::Correct { password }),
+                _ => None,
+            })
+            .unwrap_or_else(|| panic!("unexpected pressure room response: {}", text))
+    }
+    fn hold_item_subset(&mut self, items: &[String], held: &mut [bool], subset: &[bool]) {
+        for (i, item) in items.iter().enumerate() {
+            if held[i] != subset[i] {
+                if subset[i] {
+                    self.take(item);
+                } else {
+                    self.drop(item);
+                }
+                held[i] = subset[i];
+            }
+        }
+    }
+    fn search_pressure_room(&mut self, ship: &Ship, items: &[String]) -> Option<PressureSearch> {
+        let mut held = vec![false; items.len()];
+        search_subsets(items.len(), |subset| {
+            self.hold_item_subset(items, &mut held, subset);
+            self.try_to_pass_pressure_room(ship)
+        })
+    }
+}
+
+// Tries subsets in Gray-code order so only one item changes between attempts, skipping any that
+// contain a subset already too heavy or are contained in one already too light
+fn search_subsets<F>(num_items: usize, mut attempt: F) -> Option<PressureSearch>
+where
+    F: FnMut(&[bool]) -> PressureRoom,
+{
+    let mut subset = vec![false; num_items];
+    let mut counter = vec![false; num_items];
+    let mut too_heavy: Vec<Vec<bool>> = Vec::new();
+    let mut too_light: Vec<Vec<bool>> = Vec::new();
+    let mut attempts = 0;
+    loop {
+        let pruned = too_heavy.iter().any(|heavy| is_subset(heavy, &subset))
+            || too_light.iter().any(|light| is_subset(&subset, light));
+        if !pruned {
+            attempts += 1;
+            match attempt(&subset) {
+                PressureRoom::Heavy => too_light.push(subset.clone()),
+                PressureRoom::Light => too_heavy.push(subset.clone()),
+                PressureRoom::Correct { password } => {
+                    return Some(PressureSearch { password, attempts })
+                }
+            }
+        }
+        let i = counter.iter().position(|&bit| !bit)?;
+        for bit in &mut counter[..i] {
+            *bit = false;
+        }
+        counter[i] = true;
+        subset[i] = !subset[i];
+    }
+}
+
+#[derive(Clone, Copy)]
+enum PressureRoom {
+    // the alerts compare the other droids to us, so Heavy means we are too light
+    Heavy,
+    Light,
+    Correct { password: u64 },
+}
+
+struct PressureSearch {
+    password: u64,
+    attempts: usize,
+}
+
+fn is_subset(subset: &[bool], superset: &[bool]) -> bool {
+    subset
+        .iter()
+        .zip(superset.iter())
+        .all(|(&in_subset, &in_superset)| !in_subset || in_superset)
+}
+
+fn stty(tty: &File, args: &[&str]) -> Option<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(tty.try_clone().ok()?)
+        .output()
+        .ok()?;
+    if output.status.success() {
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        None
+    }
+}
+
+struct RawMode {
+    tty: File,
+    saved: String,
+}
+
+impl RawMode {
+    fn enable(tty: &File) -> Option<Self> {
+        let saved = stty(tty, &["-g"])?;
+        stty(tty, &["-icanon", "-echo", "min", "1"])?;
+        Some(Self {
+            tty: tty.try_clone().ok()?,
+            saved,
+        })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        stty(&self.tty, &[&self.saved]);
+    }
+}
+
+enum Key {
+    Char(char),
+    Enter,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    Kill,
+    EndOfFile,
+    Other,
+}
+
+struct LineEditor {
+    tty: Option<File>,
+    raw_mode: Option<RawMode>,
+    history: Vec<String>,
+}
+
+impl LineEditor {
+    fn new() -> Self {
+        let tty = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open("/dev/tty")
+            .ok();
+        let raw_mode = tty.as_ref().and_then(RawMode::enable);
+        Self {
+            tty,
+            raw_mode,
+            history: Vec::new(),
+        }
+    }
+    fn read_byte(&mut self) -> Option<u8> {
+        let mut byte = [0];
+        match self.tty.as_mut()?.read(&mut byte) {
+            Ok(1) => Some(byte[0]),
+            _ => None,
+        }
+    }
+    fn read_key(&mut self) -> Key {
+        match self.read_byte() {
+            None | Some(4) => Key::EndOfFile,
+            Some(b'\n') | Some(b'\r') => Key::Enter,
+            Some(8) | Some(127) => Key::Backspace,
+            Some(1) => Key::Home,
+            Some(5) => Key::End,
+            Some(21) => Key::Kill,
+            Some(27) => match (self.read_byte(), self.read_byte()) {
+                (Some(b'['), Some(b'A')) => Key::Up,
+                (Some(b'['), Some(b'B')) => Key::Down,
+                (Some(b'['), Some(b'C')) => Key::Right,
+                (Some(b'['), Some(b'D')) => Key::Left,
+                (Some(b'['), Some(b'H')) => Key::Home,
+                (Some(b'['), Some(b'F')) => Key::End,
+                (Some(b'['), Some(b'3')) => {
+                    self.read_byte();
+                    Key::Delete
+                }
+                _ => Key::Other,
+            },
+            Some(byte) if (32..127).contains(&byte) => Key::Char(byte as char),
+            Some(_) => Key::Other,
+        }
+    }
+    fn redraw(prompt: &str, line: &[char], cursor: usize) {
+        print!("\r\x1b[K{}{}", prompt, line.iter().collect::<String>());
+        if cursor < line.len() {
+            print!("\x1b[{}D", line.len() - cursor);
+        }
+        std::io::stdout().flush().unwrap();
+    }
+    fn read_line(&mut self, prompt: &str) -> Option<String> {
+        if self.raw_mode.is_none() {
+            print!("{}", prompt);
+            std::io::stdout().flush().unwrap();
+            let mut line = String::new();
+            BufReader::new(self.tty.as_ref()?)
+                .read_line(&mut line)
+                .ok()?;
+            return if line.is_empty() {
+                None
+            } else {
+                Some(line.trim_end().to_string())
+            };
+        }
+        let mut line = Vec::new();
+        let mut cursor = 0;
+        let mut history_index = self.history.len();
+        Self::redraw(prompt, &line, cursor);
+        loop {
+            match self.read_key() {
+                Key::Char(c) => {
+                    line.insert(cursor, c);
+                    cursor += 1;
+                }
+                Key::Enter => break,
+                Key::Backspace if cursor > 0 => {
+                    cursor -= 1;
+                    line.remove(cursor);
+                }
+                Key::Delete if cursor < line.len() => {
+                    line.remove(cursor);
+                }
+                Key::Left if cursor > 0 => cursor -= 1,
+                Key::Right if cursor < line.len() => cursor += 1,
+                Key::Home => cursor = 0,
+                Key::End => cursor = line.len(),
+                Key::Kill => {
+                    line.clear();
+                    cursor = 0;
+                }
+                Key::Up if history_index > 0 => {
+                    history_index -= 1;
+                    line = self.history[history_index].chars().collect();
+                    cursor = line.len();
+                }
+                Key::Down if history_index < self.history.len() => {
+                    history_index += 1;
+                    line = self
+                        .history
+                        .get(history_index)
+                        .map_or(Vec::new(), |entry| entry.chars().collect());
+                    cursor = line.len();
+                }
+                Key::EndOfFile if line.is_empty() => {
+                    println!();
+                    return None;
+                }
+                _ => (),
+            }
+            Self::redraw(prompt, &line, cursor);
+        }
+        println!();
+        let line = line.into_iter().collect::<String>();
+        if !line.trim().is_empty() && self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+        }
+        Some(line)
+    }
+}
+
+const PLAY_HELP: &str = "\
+commands are sent to the game, except for:
+  n, e, s, w               move in a direction
+  save <slot>              snapshot the machine
+  load <slot>              restore a snapshot
+  goto <room>              walk to a discovered room
+  explore                  discover every room without moving
+  collect all safe items   pick up every item that can't hurt you
+  map                      show the rooms discovered so far
+  help                     show this message
+  quit                     leave the game";
+
+// Everything a save slot restores. Items known to be unsafe stay known across loads.
+#[derive(Clone)]
+struct Snapshot {
+    computer: Computer,
+    map: ShipMap,
+    room: Option<String>,
+}
+
+struct Player {
+    computer: Computer,
+    map: ShipMap,
+    room: Option<String>,
+    saves: HashMap<String, Snapshot>,
+    unsafe_items: HashSet<String>,
+}
+
+impl Player {
+    fn new(program: &[i128]) -> Self {
+        let mut computer = Computer::new(program);
+        computer.echo = Echo::Off;
+        let mut player = Self {
+            computer,
+            map: ShipMap::default(),
+            room: None,
+            saves: HashMap::new(),
+            unsafe_items: HashSet::new(),
+        };
+        let text = player.computer.run_until_prompt();
+        print!("{}", text);
+        player.observe(None, &text);
+        player
+    }
+    fn here(&mut self) -> Option<&mut Room> {
+        let rooms = &mut self.map.rooms;
+        self.room.as_ref().and_then(move |room| rooms.get_mut(room))
+    }
+    fn observe(&mut self, mut moved: Option<Direction>, text: &str) {
+        let events = match parse_adventure(text) {
+            Ok(events) => events,
+            Err(error) => return println!("({})", error),
+        };
+        for event in events {
+            match event {
+                AdventureEvent::RoomEntered(room) | AdventureEvent::Ejected(room) => {
+                    self.map.add_room(&room);
+                    if let (Some(direction), Some(from)) = (moved.take(), self.room.as_ref()) {
+                        self.map.connect(from, direction, &room.name);
+                    }
+                    self.room = Some(room.name);
+                }
+                AdventureEvent::ItemTaken(item) => {
+                    if let Some(room) = self.here() {
+                        room.items.retain(|here| here != &item);
+                    }
+                }
+                AdventureEvent::ItemDropped(item) => {
+                    if let Some(room) = self.here() {
+                        room.items.push(item);
+                    }
+                }
+                _ => (),
+            }
         }
     }
-    fn take_item_subset(&mut self, subset: u8) {
-        for i in 0..ALL_ITEMS.len() {
-            if subset & (1 << i) != 0 {
-                self.take(ALL_ITEMS[i]);
+    fn act(&mut self, command: &str) -> bool {
+        match self.computer.send(command) {
+            Response::Prompt(text) => {
+                print!("{}", text);
+                self.observe(Direction::parse(command), &text);
+                true
             }
+            Response::Halt(text) => {
+                print!("{}", text);
+                println!("(the game is over; load a save or quit)");
+                false
+            }
+            Response::Runaway => {
+                println!("(the game stopped responding; load a save or quit)");
+                false
+            }
+        }
+    }
+    fn act_visibly(&mut self, command: &str) -> bool {
+        println!("> {}", command);
+        self.act(command)
+    }
+    fn find_room(&self, query: &str) -> Option<String> {
+        let query = query.to_lowercase();
+        let names = self.map.rooms.keys();
+        let exact = names.clone().find(|name| name.to_lowercase() == query);
+        let mut prefixed = names.filter(|name| name.to_lowercase().starts_with(&query));
+        match (exact, prefixed.next(), prefixed.next()) {
+            (Some(name), _, _) | (None, Some(name), None) => Some(name.clone()),
+            _ => None,
         }
     }
-    fn drop_item_subset(&mut self, subset: u8) {
-        for i in 0..ALL_ITEMS.len() {
-            if subset & (1 << i) != 0 {
-                self.drop(ALL_ITEMS[i]);
+    fn goto(&mut self, query: &str) -> bool {
+        let target = match self.find_room(query) {
+            Some(target) => target,
+            None => {
+                println!("(no discovered room matches {})", query);
+                return true;
+            }
+        };
+        let path = match self
+            .room
+            .as_ref()
+            .and_then(|room| self.map.path(room, &target))
+        {
+            Some(path) => path,
+            None => {
+                println!("(no known route to {})", target);
+                return true;
+            }
+        };
+        path.into_iter()
+            .all(|direction| self.act_visibly(direction.to_str()))
+    }
+    fn explore(&mut self) {
+        let room = match self.room.as_ref().and_then(|room| self.map.rooms.get(room)) {
+            Some(room) => room.clone(),
+            None => return,
+        };
+        let mut probe = self.computer.clone();
+        match probe.explore_from(&room) {
+            Ok(ship) => {
+                self.map.merge(ship.map);
+                println!("(discovered {} rooms)", self.map.rooms.len());
             }
+            Err(error) => println!("(exploring failed from here: {})", error),
         }
     }
-    fn try_to_pass_pressure_room_with_subset(&mut self, ship: &Ship, subset: u8) -> PressureRoom {
-        self.take_item_subset(subset);
-        let result = self.try_to_pass_pressure_room(ship);
-        if let PressureRoom::Correct = result {
-            panic!("it worked {}", subset);
+    fn collect_all_safe_items(&mut self) {
+        let items = self
+            .map
+            .rooms
+            .values()
+            .flat_map(|room| {
+                room.items
+                    .iter()
+                    .map(move |item| (room.name.clone(), item.clone()))
+            })
+            .filter(|(_, item)| !self.unsafe_items.contains(item))
+            .collect::<Vec<_>>();
+        for (room_name, item) in items {
+            let here = match self.room.clone() {
+                Some(here) => here,
+                None => return,
+            };
+            let path = match self.map.path(&here, &room_name) {
+                Some(path) => path,
+                None => continue,
+            };
+            let retreat = match path.last() {
+                Some(direction) => direction.opposite(),
+                None => match self.map.doors_from(&here).next() {
+                    Some((direction, _)) => direction,
+                    None => continue,
+                },
+            };
+            if !self.computer.item_is_safe_along(&path, &item, retreat) {
+                println!("(leaving the {} alone)", item);
+                self.unsafe_items.insert(item);
+                continue;
+            }
+            if !self.goto(&room_name) || !self.act_visibly(&format!("take {}", item)) {
+                return;
+            }
         }
-        self.drop_item_subset(subset);
-        result
     }
-    fn try_to_pass_pressure_room_with_all_subsets(&mut self, ship: &Ship) {
-        for i in 0..=255 {
-            self.try_to_pass_pressure_room_with_subset(ship, i);
+    fn execute(&mut self, line: &str) -> bool {
+        let line = line.trim();
+        let mut words = line.splitn(2, ' ');
+        let (command, argument) = (words.next().unwrap_or(""), words.next().unwrap_or(""));
+        match (command, argument) {
+            ("", _) => (),
+            ("quit", "") | ("exit", "") => return false,
+            ("help", "") => println!("{}", PLAY_HELP),
+            ("map", "") if self.map.rooms.is_empty() => println!("(no rooms discovered)"),
+            ("map", "") => print!("{}", self.map.to_ascii()),
+            ("save", slot) if !slot.is_empty() => {
+                let snapshot = Snapshot {
+                    computer: self.computer.clone(),
+                    map: self.map.clone(),
+                    room: self.room.clone(),
+                };
+                self.saves.insert(slot.to_string(), snapshot);
+                println!("(saved {})", slot);
+            }
+            ("load", slot) if !slot.is_empty() => match self.saves.get(slot).cloned() {
+                Some(snapshot) => {
+                    self.computer = snapshot.computer;
+                    self.map = snapshot.map;
+                    self.room = snapshot.room;
+                    println!("(loaded {})", slot);
+                }
+                None => println!("(no save called {})", slot),
+            },
+            ("goto", room) if !room.is_empty() => {
+                self.goto(room);
+            }
+            ("explore", "") => self.explore(),
+            ("collect", "all safe items") => self.collect_all_safe_items(),
+            ("n", "") => {
+                self.act("north");
+            }
+            ("e", "") => {
+                self.act("east");
+            }
+            ("s", "") => {
+                self.act("south");
+            }
+            ("w", "") => {
+                self.act("west");
+            }
+            _ => {
+                self.act(line);
+            }
         }
+        true
     }
 }
 
-enum PressureRoom {
-    Heavy,
-    Light,
-    Correct,
-}
-
-const ALL_ITEMS: &[&str] = &[
-    "astrolabe",
-    "bowl of rice",
-    "cake",
-    "fuel cell",
-    "monolith",
-    "ornament",
-    "planetoid",
-    "shell",
-];
+fn play(program: &[i128], args: &[String]) {
+    let mut record = None;
+    let mut replay = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let path = args.next().expect("expected a path after the option");
+        match arg.as_str() {
+            "--record" => record = Some(File::create(path).unwrap()),
+            "--replay" => {
+                replay = std::fs::read_to_string(path)
+                    .unwrap()
+                    .lines()
+                    .map(str::to_string)
+                    .collect()
+            }
+            other => panic!("unknown option: {}", other),
+        }
+    }
+    let mut player = Player::new(program);
+    let mut editor = LineEditor::new();
+    let mut replay = replay.into_iter();
+    loop {
+        let line = match replay.next() {
+            Some(line) => {
+                println!("> {}", line);
+                line
+            }
+            None => match editor.read_line("> ") {
+                Some(line) => line,
+                None => break,
+            },
+        };
+        if let Some(record) = record.as_mut() {
+            writeln!(record, "{}", line).unwrap();
+        }
+        if !player.execute(&line) {
+            break;
+        }
+    }
+}
 
 fn main() {
     let mut input_string = String::new();
@@ -777,9 +1720,330 @@ fn main() {
         .split(",")
         .map(|s| s.trim().parse::<i128>().unwrap())
         .collect::<Vec<_>>();
+    let args = std::env::args().collect::<Vec<_>>();
+    if args.get(1).map(String::as_str) == Some("play") {
+        return play(&program, &args[2..]);
+    }
     let mut computer = Computer::new(&program);
-    let ship = computer.explore();
-    computer.collect_all_items(&ship);
-    computer.drop_all_items_in_security_room(&ship);
-    computer.try_to_pass_pressure_room_with_all_subsets(&ship);
+    let ship = computer
+        .explore()
+        .unwrap_or_else(|error| panic!("exploring failed: {}", error));
+    match args.get(1).map(String::as_str) {
+        None | Some("solve") => (),
+        Some("dot") => return print!("{}", ship.map.to_dot()),
+        Some("map") => return print!("{}", ship.map.to_ascii()),
+        Some(other) => panic!("unknown mode: {} (expected solve, dot, map or play)", other),
+    }
+    let items = computer.collect_safe_items(&ship);
+    computer.drop_all_items_in_security_room(&ship, &items);
+    let search = computer
+        .search_pressure_room(&ship, &items)
+        .expect("no combination of items passes the pressure-sensitive floor");
+    println!("passed the floor after {} attempts", search.attempts);
+    println!("{}", search.password);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ENGINEERING: &str = "
+
+
+== Engineering ==
+You see a whiteboard with plans for Springdroid v2.
+
+Doors here lead:
+- east
+
+Items here:
+- bowl of rice
+
+Command?
+";
+
+    const PRESSURE_FLOOR: &str = "
+
+
+== Pressure-Sensitive Floor ==
+Analyzing...
+
+Doors here lead:
+- south
+
+A loud, robotic voice says \"Alert! Droids on this ship are heavier than the detected value!\" and you are ejected back to the checkpoint.
+
+
+
+== Security Checkpoint ==
+In the next room, a pressure-sensitive floor will verify your identity.
+
+Doors here lead:
+- north
+- east
+
+Command?
+";
+
+    const COCKPIT: &str = "
+
+
+== Pressure-Sensitive Floor ==
+Analyzing...
+
+Doors here lead:
+- south
+
+A loud, robotic voice says \"Analysis complete! You may proceed.\" and you enter the cockpit.
+Santa notices your small droid, looks puzzled for a moment, realizes what has happened, and radios your ship directly.
+\"Oh, hello! You should be able to get in by typing 1109393410 on the keypad at the main airlock.\"
+";
+
+    fn room(name: &str, description: &str, exits: &[Direction], items: &[&str]) -> Room {
+        Room {
+            exits: exits.to_vec(),
+            name: name.to_string(),
+            description: description.to_string(),
+            items: items.iter().map(|item| item.to_string()).collect(),
+        }
+    }
+
+    // A floor that weighs the held items against a target, like the real one
+    fn weigh(weights: &[u64], target: u64, subset: &[bool]) -> PressureRoom {
+        let weight = weights
+            .iter()
+            .zip(subset.iter())
+            .filter(|&(_, &held)| held)
+            .map(|(&weight, _)| weight)
+            .sum::<u64>();
+        match weight.cmp(&target) {
+            std::cmp::Ordering::Less => PressureRoom::Heavy,
+            std::cmp::Ordering::Greater => PressureRoom::Light,
+            std::cmp::Ordering::Equal => PressureRoom::Correct { password: weight },
+        }
+    }
+
+    #[test]
+    fn subsets() {
+        assert!(is_subset(&[false, false], &[false, false]));
+        assert!(is_subset(&[true, false], &[true, true]));
+        assert!(!is_subset(&[true, false], &[false, true]));
+        assert!(!is_subset(&[true, true], &[true, false]));
+    }
+
+    #[test]
+    fn search_finds_the_matching_weight() {
+        let weights = [3, 5, 8, 13, 21, 34, 55, 89];
+        let target = 5 + 13 + 34 + 89;
+        let mut tried = Vec::new();
+        let search = search_subsets(weights.len(), |subset| {
+            tried.push(subset.to_vec());
+            weigh(&weights, target, subset)
+        })
+        .unwrap();
+        assert_eq!(search.password, target);
+        assert_eq!(search.attempts, tried.len());
+        assert!(search.attempts < 1 << weights.len());
+    }
+
+    #[test]
+    fn search_never_tries_a_pruned_subset() {
+        let weights = [1, 2, 4, 8, 16, 32];
+        let mut tried: Vec<(Vec<bool>, PressureRoom)> = Vec::new();
+        let search = search_subsets(weights.len(), |subset| {
+            for (earlier, room) in &tried {
+                match room {
+                    PressureRoom::Light => assert!(!is_subset(earlier, subset)),
+                    PressureRoom::Heavy => assert!(!is_subset(subset, earlier)),
+                    PressureRoom::Correct { .. } => unreachable!(),
+                }
+            }
+            let room = weigh(&weights, 1000, subset);
+            tried.push((subset.to_vec(), room));
+            room
+        });
+        assert!(search.is_none());
+        assert!(tried.len() < 1 << weights.len());
+    }
+
+    fn small_map() -> ShipMap {
+        let mut map = ShipMap::default();
+        map.add_room(&room("Hull Breach", "You got in.", &[Direction::East], &[]));
+        map.add_room(&room(
+            "Storage",
+            "The \"boxes\" contain boxes.",
+            &[Direction::South, Direction::West],
+            &["cake"],
+        ));
+        map.add_room(&room("Sick Bay", "Medicine.", &[Direction::North], &[]));
+        map.connect("Hull Breach", Direction::East, "Storage");
+        map.connect("Storage", Direction::South, "Sick Bay");
+        map
+    }
+
+    #[test]
+    fn map_to_dot() {
+        let expected = r#"graph ship {
+    node [shape=box];
+    "Hull Breach" [label="Hull Breach\n(start)", tooltip="You got in."];
+    "Sick Bay" [label="Sick Bay", tooltip="Medicine."];
+    "Storage" [label="Storage\n- cake", tooltip="The \"boxes\" contain boxes."];
+    "Hull Breach" -- "Storage" [taillabel="east", headlabel="west"];
+    "Sick Bay" -- "Storage" [taillabel="north", headlabel="south"];
+}
+"#;
+        assert_eq!(small_map().to_dot(), expected);
+    }
+
+    #[test]
+    fn map_to_ascii() {
+        let expected = "\
+[ 0]---[ 1]
+         |
+       [ 2]
+
+ 0: Hull Breach (start)
+ 1: Storage [cake]
+ 2: Sick Bay
+";
+        assert_eq!(small_map().to_ascii(), expected);
+    }
+
+    #[test]
+    fn map_lists_doors_it_cannot_draw() {
+        let mut map = ShipMap::default();
+        for name in &["A", "B", "C", "D", "E"] {
+            map.add_room(&room(name, "", &[], &[]));
+        }
+        map.connect("A", Direction::East, "B");
+        map.connect("A", Direction::South, "C");
+        map.connect("B", Direction::South, "E");
+        map.connect("C", Direction::East, "D");
+        let ascii = map.to_ascii();
+        assert!(ascii.starts_with("[ 0]---[ 1]\n  |      |\n[ 2]   [ 3]   [ 4]\n"));
+        assert!(ascii.ends_with("not drawn: C east to D\n"));
+        assert_eq!(
+            map.path("C", "E"),
+            Some(vec![Direction::North, Direction::East, Direction::South])
+        );
+    }
+
+    #[test]
+    fn explore_reports_a_halted_program() {
+        let mut computer = Computer::new(&[104, 10, 99]);
+        let error = computer.explore().unwrap_err();
+        assert!(matches!(error, ExploreError::Halted(_)), "{}", error);
+    }
+
+    #[test]
+    fn load_restores_the_map() {
+        let program = include_str!("../input")
+            .split(',')
+            .map(|s| s.trim().parse::<i128>().unwrap())
+            .collect::<Vec<_>>();
+        let mut player = Player::new(&program);
+        for line in &["e", "n", "w", "save before", "take bowl of rice", "e"] {
+            assert!(player.execute(line));
+        }
+        assert_eq!(player.room.as_deref(), Some("Sick Bay"));
+        assert!(player.map.rooms["Engineering"].items.is_empty());
+        player.execute("load before");
+        assert_eq!(player.room.as_deref(), Some("Engineering"));
+        assert_eq!(player.map.rooms["Engineering"].items, vec!["bowl of rice"]);
+    }
+
+    #[test]
+    fn room_with_items() {
+        assert_eq!(
+            parse_adventure(ENGINEERING).unwrap(),
+            vec![
+                AdventureEvent::RoomEntered(room(
+                    "Engineering",
+                    "You see a whiteboard with plans for Springdroid v2.",
+                    &[Direction::East],
+                    &["bowl of rice"],
+                )),
+                AdventureEvent::Prompt,
+            ]
+        );
+    }
+
+    #[test]
+    fn take_drop_and_inventory() {
+        let text = "
+You take the bowl of rice.
+
+Command?
+
+You drop the bowl of rice.
+
+Command?
+
+Items in your inventory:
+- cake
+- planetoid
+
+Command?
+
+You aren't carrying any items.
+
+Command?
+
+You can't go that way.
+
+Command?
+";
+        assert_eq!(
+            parse_adventure(text).unwrap(),
+            vec![
+                AdventureEvent::ItemTaken("bowl of rice".to_string()),
+                AdventureEvent::Prompt,
+                AdventureEvent::ItemDropped("bowl of rice".to_string()),
+                AdventureEvent::Prompt,
+                AdventureEvent::Inventory(vec!["cake".to_string(), "planetoid".to_string()]),
+                AdventureEvent::Prompt,
+                AdventureEvent::Inventory(Vec::new()),
+                AdventureEvent::Prompt,
+                AdventureEvent::Refused(Refusal::NoDoor),
+                AdventureEvent::Prompt,
+            ]
+        );
+    }
+
+    #[test]
+    fn pressure_floor_alert_and_ejection() {
+        let events = parse_adventure(PRESSURE_FLOOR).unwrap();
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[1], AdventureEvent::Alert(Alert::Heavy));
+        assert_eq!(
+            events[2],
+            AdventureEvent::Ejected(room(
+                "Security Checkpoint",
+                "In the next room, a pressure-sensitive floor will verify your identity.",
+                &[Direction::North, Direction::East],
+                &[],
+            ))
+        );
+        assert_eq!(
+            current_room(&events).map(|room| room.name.as_str()),
+            Some("Security Checkpoint")
+        );
+    }
+
+    #[test]
+    fn password() {
+        assert_eq!(
+            parse_adventure(COCKPIT).unwrap().last(),
+            Some(&AdventureEvent::Password(1109393410))
+        );
+    }
+
+    #[test]
+    fn unrecognised_output() {
+        let error = parse_adventure(&format!("{}\nThe droid beeps.\n\nCommand?\n", ENGINEERING))
+            .unwrap_err();
+        assert_eq!(error.text, "The droid beeps.");
+        let error = parse_adventure("\nItems here:\n* cake\n").unwrap_err();
+        assert_eq!(error.text, "Items here:\n* cake");
+    }
 }
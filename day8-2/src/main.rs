@@ -17,6 +17,18 @@ fn main() {
         .collect::<Vec<_>>();
     let rendered = render(&digits, SIZE);
     display(&rendered, WIDTH, HEIGHT);
+    let rows = rendered
+        .chunks_exact(WIDTH)
+        .map(|row| {
+            row.iter()
+                .map(|&colour| matches!(colour, Colour::White))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    match ocr::recognise(&rows) {
+        Ok(text) => println!("{}", text),
+        Err(error) => println!("{}", error),
+    }
 }
 
 #[derive(Clone, Copy)]
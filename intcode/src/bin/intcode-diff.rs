@@ -0,0 +1,24 @@
+use std::io::Read;
+
+const MAX_STEPS: usize = 100_000_000;
+
+fn main() {
+    let inputs = std::env::args()
+        .skip(1)
+        .map(|s| s.parse::<i128>().unwrap())
+        .collect::<Vec<_>>();
+    let mut input_string = String::new();
+    std::io::stdin()
+        .lock()
+        .read_to_string(&mut input_string)
+        .unwrap();
+    let program = input_string
+        .split(',')
+        .map(|s| s.trim().parse::<i128>().unwrap())
+        .collect::<Vec<_>>();
+    let report = intcode::diff::compare(&program, &inputs, MAX_STEPS);
+    print!("{}", report);
+    if report.diverged() {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,40 @@
+use intcode::computer::{Extensions, Param};
+use intcode::disasm;
+use std::io::Read;
+
+fn parse_extension(s: &str) -> (i128, String, Vec<Param>) {
+    let (opcode, rest) = s.split_at(s.find('=').expect("expected OPCODE=MNEMONIC[:PARAMS]"));
+    let mut parts = rest[1..].splitn(2, ':');
+    let mnemonic = parts.next().unwrap().to_string();
+    let params = parts
+        .next()
+        .unwrap_or("")
+        .chars()
+        .map(|c| match c {
+            'r' => Param::Read,
+            'w' => Param::Write,
+            other => panic!("unexpected param kind: {}", other),
+        })
+        .collect();
+    (opcode.parse().unwrap(), mnemonic, params)
+}
+
+fn main() {
+    let mut extensions = Extensions::default();
+    for arg in std::env::args().skip(1) {
+        let (opcode, mnemonic, params) = parse_extension(&arg);
+        extensions.declare(opcode, &mnemonic, &params);
+    }
+    let mut input_string = String::new();
+    std::io::stdin()
+        .lock()
+        .read_to_string(&mut input_string)
+        .unwrap();
+    let program = input_string
+        .split(',')
+        .map(|s| s.trim().parse::<i128>().unwrap())
+        .collect::<Vec<_>>();
+    for line in disasm::disassemble(&program, &extensions) {
+        println!("{}", line);
+    }
+}
@@ -0,0 +1,48 @@
+use intcode::explore::{self, Explorer};
+use std::io::Read;
+
+const DEFAULT_SAMPLES: usize = 1000;
+const DROID_SEED: u64 = 0;
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let target = args.first().map(String::as_str).unwrap_or("arcade");
+    let max_samples = args
+        .get(1)
+        .map(|s| s.parse().unwrap())
+        .unwrap_or(DEFAULT_SAMPLES);
+    let mut input_string = String::new();
+    std::io::stdin()
+        .lock()
+        .read_to_string(&mut input_string)
+        .unwrap();
+    let program = input_string
+        .split(',')
+        .map(|s| s.trim().parse::<i128>().unwrap())
+        .collect::<Vec<_>>();
+    let start = Explorer::new(&program).snapshot();
+    let explorer = match target {
+        "arcade" => explore::explore_arcade(&program, max_samples),
+        "droid" => explore::explore_droid(&program, max_samples, DROID_SEED),
+        other => panic!("unknown target: {} (expected arcade or droid)", other),
+    }
+    .unwrap();
+    println!(
+        "{} samples, {} writes",
+        explorer.samples(),
+        explorer.writes().len()
+    );
+    println!("changed since start:");
+    for change in start.diff(&explorer.snapshot()) {
+        println!(
+            "  [{}] {} -> {}",
+            change.address, change.before, change.after
+        );
+    }
+    println!("correlations:");
+    for correlation in explorer.correlations() {
+        println!("  {}", correlation);
+    }
+    println!("memory map:");
+    print!("{}", explorer.memory_map());
+}
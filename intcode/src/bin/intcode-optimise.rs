@@ -0,0 +1,53 @@
+use std::io::Read;
+
+const MAX_STEPS: usize = 100_000_000;
+
+fn parse_values(s: &str) -> Vec<i128> {
+    s.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<i128>().unwrap())
+        .collect()
+}
+
+fn parse_session(arg: &str) -> Vec<i128> {
+    match arg.strip_prefix('@') {
+        Some(path) => parse_values(&std::fs::read_to_string(path).unwrap()),
+        None => parse_values(arg),
+    }
+}
+
+fn main() {
+    let sessions = std::env::args()
+        .skip(1)
+        .map(|arg| parse_session(&arg))
+        .collect::<Vec<_>>();
+    let mut input_string = String::new();
+    std::io::stdin()
+        .lock()
+        .read_to_string(&mut input_string)
+        .unwrap();
+    let program = parse_values(&input_string);
+    match intcode::optimise::optimise(&program, &sessions, MAX_STEPS) {
+        Ok(optimised) => {
+            for rewrite in &optimised.rewrites {
+                eprintln!("{}", rewrite);
+            }
+            for (i, report) in optimised.sessions.iter().enumerate() {
+                eprintln!(
+                    "session {}: {} outputs, {} -> {} instructions executed",
+                    i, report.outputs, report.original_steps, report.optimised_steps
+                );
+            }
+            let words = optimised
+                .program
+                .iter()
+                .map(|word| word.to_string())
+                .collect::<Vec<_>>();
+            println!("{}", words.join(","));
+        }
+        Err(error) => {
+            eprintln!("not equivalent: {}", error);
+            std::process::exit(1);
+        }
+    }
+}
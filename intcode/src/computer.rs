@@ -0,0 +1,903 @@
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::fmt;
+use std::ops::{Deref, DerefMut, Range};
+
+#[derive(Debug)]
+pub enum ParamMode {
+    Positional,
+    Immediate,
+    Relative,
+}
+
+struct ParamArgs {
+    param: i128,
+    relative_base: i128,
+}
+
+impl ParamMode {
+    pub fn from_i128(i128: i128) -> Option<Self> {
+        match i128 {
+            0 => Some(Self::Positional),
+            1 => Some(Self::Immediate),
+            2 => Some(Self::Relative),
+            _ => None,
+        }
+    }
+    fn read(
+        &self,
+        ParamArgs {
+            param,
+            relative_base,
+        }: ParamArgs,
+        memory: &mut Memory,
+    ) -> i128 {
+        match self {
+            Self::Positional => {
+                assert!(param >= 0);
+                memory.read(param as usize)
+            }
+            Self::Immediate => param,
+            Self::Relative => {
+                let address = param + relative_base;
+                assert!(address >= 0);
+                memory.read(address as usize)
+            }
+        }
+    }
+    fn write(
+        &self,
+        ParamArgs {
+            param,
+            relative_base,
+        }: ParamArgs,
+        value: i128,
+        memory: &mut Memory,
+    ) {
+        match self {
+            Self::Positional => {
+                assert!(param >= 0);
+                memory.write(param as usize, value);
+            }
+            Self::Immediate => panic!("attempted to write in immediate mode"),
+            Self::Relative => {
+                let address = param + relative_base;
+                assert!(address >= 0);
+                memory.write(address as usize, value);
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParamModes {
+    encoded: i128,
+}
+
+impl ParamModes {
+    pub fn nth_code(&self, n: u32) -> i128 {
+        (self.encoded / 10_i128.pow(n)) % 10
+    }
+    pub fn nth(&self, n: u32) -> Option<ParamMode> {
+        ParamMode::from_i128(self.nth_code(n))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Opcode {
+    Add,
+    Multiply,
+    Input,
+    Output,
+    JumpIfTrue,
+    JumpIfFalse,
+    LessThan,
+    Equals,
+    AdjustRelativeBase,
+    Halt,
+    Extension(i128),
+}
+
+impl Opcode {
+    fn builtin(i128: i128) -> Option<Self> {
+        match i128 {
+            1 => Some(Self::Add),
+            2 => Some(Self::Multiply),
+            3 => Some(Self::Input),
+            4 => Some(Self::Output),
+            5 => Some(Self::JumpIfTrue),
+            6 => Some(Self::JumpIfFalse),
+            7 => Some(Self::LessThan),
+            8 => Some(Self::Equals),
+            9 => Some(Self::AdjustRelativeBase),
+            99 => Some(Self::Halt),
+            _ => None,
+        }
+    }
+    pub fn from_i128(i128: i128, extensions: &Extensions) -> Option<Self> {
+        Self::builtin(i128).or_else(|| {
+            if extensions.get(i128).is_some() {
+                Some(Self::Extension(i128))
+            } else {
+                None
+            }
+        })
+    }
+    pub fn mnemonic<'a>(&self, extensions: &'a Extensions) -> &'a str {
+        match self {
+            Self::Add => "ADD",
+            Self::Multiply => "MUL",
+            Self::Input => "IN",
+            Self::Output => "OUT",
+            Self::JumpIfTrue => "JT",
+            Self::JumpIfFalse => "JF",
+            Self::LessThan => "LT",
+            Self::Equals => "EQ",
+            Self::AdjustRelativeBase => "ARB",
+            Self::Halt => "HALT",
+            Self::Extension(opcode) => &extensions.get(*opcode).unwrap().mnemonic,
+        }
+    }
+    pub fn params<'a>(&self, extensions: &'a Extensions) -> &'a [Param] {
+        use Param::{Read, Write};
+        match self {
+            Self::Add | Self::Multiply | Self::LessThan | Self::Equals => &[Read, Read, Write],
+            Self::JumpIfTrue | Self::JumpIfFalse => &[Read, Read],
+            Self::Input => &[Write],
+            Self::Output | Self::AdjustRelativeBase => &[Read],
+            Self::Halt => &[],
+            Self::Extension(opcode) => &extensions.get(*opcode).unwrap().params,
+        }
+    }
+}
+
+#[derive(Default, Debug)]
+pub struct IoBuffer {
+    values: VecDeque<i128>,
+}
+
+pub enum Echo {
+    On,
+    Off,
+}
+
+impl IoBuffer {
+    pub fn read(&mut self) -> Option<i128> {
+        self.values.pop_front()
+    }
+    pub fn drain<'a>(&'a mut self) -> impl 'a + Iterator<Item = i128> {
+        self.values.drain(..)
+    }
+    pub fn drain_ascii_string(&mut self) -> String {
+        let mut buffer = Vec::new();
+        while let Some(&next) = self.values.front() {
+            if next <= 127 {
+                buffer.push(self.values.pop_front().unwrap() as u8);
+            } else {
+                break;
+            }
+        }
+        String::from_utf8(buffer).unwrap()
+    }
+    pub fn write(&mut self, value: i128) {
+        self.values.push_back(value)
+    }
+    pub fn write_ascii_string(&mut self, s: &str, echo: Echo) {
+        match echo {
+            Echo::On => print!("{}", s),
+            Echo::Off => (),
+        }
+        for byte in s.bytes() {
+            self.write(byte as i128);
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+#[derive(Debug)]
+pub struct Instruction {
+    pub opcode: Opcode,
+    pub param_modes: ParamModes,
+}
+
+pub enum Status {
+    Running,
+    WaitForInput,
+    WroteOutput,
+    Halt,
+}
+
+#[derive(Default, Debug)]
+pub struct State {
+    pub ip: usize,
+    pub relative_base: i128,
+}
+
+impl Instruction {
+    pub fn decode(encoded: i128, extensions: &Extensions) -> Option<Self> {
+        let opcode = Opcode::from_i128(encoded % 100, extensions)?;
+        let param_modes = ParamModes {
+            encoded: encoded / 100,
+        };
+        Some(Self {
+            opcode,
+            param_modes,
+        })
+    }
+    fn run(
+        &self,
+        memory: &mut Memory,
+        state: &mut State,
+        input_buffer: &mut IoBuffer,
+        output_buffer: &mut IoBuffer,
+    ) -> Result<Status, Error> {
+        let relative_base = state.relative_base;
+        let ip = state.ip;
+        let mode = |n| {
+            self.param_modes.nth(n).ok_or(Error::UnknownParamMode {
+                ip,
+                mode: self.param_modes.nth_code(n),
+            })
+        };
+        Ok(match self.opcode {
+            Opcode::Add => {
+                let lhs_param = memory[state.ip + 1];
+                let rhs_param = memory[state.ip + 2];
+                let dst_param = memory[state.ip + 3];
+                let lhs = mode(0)?.read(
+                    ParamArgs {
+                        param: lhs_param,
+                        relative_base,
+                    },
+                    memory,
+                );
+                let rhs = mode(1)?.read(
+                    ParamArgs {
+                        param: rhs_param,
+                        relative_base,
+                    },
+                    memory,
+                );
+                let value = lhs + rhs;
+                mode(2)?.write(
+                    ParamArgs {
+                        param: dst_param,
+                        relative_base,
+                    },
+                    value,
+                    memory,
+                );
+                state.ip += 4;
+                Status::Running
+            }
+            Opcode::Multiply => {
+                let lhs_param = memory[state.ip + 1];
+                let rhs_param = memory[state.ip + 2];
+                let dst_param = memory[state.ip + 3];
+                let lhs = mode(0)?.read(
+                    ParamArgs {
+                        param: lhs_param,
+                        relative_base,
+                    },
+                    memory,
+                );
+                let rhs = mode(1)?.read(
+                    ParamArgs {
+                        param: rhs_param,
+                        relative_base,
+                    },
+                    memory,
+                );
+                let value = lhs * rhs;
+                mode(2)?.write(
+                    ParamArgs {
+                        param: dst_param,
+                        relative_base,
+                    },
+                    value,
+                    memory,
+                );
+                state.ip += 4;
+                Status::Running
+            }
+            Opcode::Input => {
+                if let Some(value) = input_buffer.read() {
+                    let param = memory[state.ip + 1];
+                    mode(0)?.write(
+                        ParamArgs {
+                            param,
+                            relative_base,
+                        },
+                        value,
+                        memory,
+                    );
+                    state.ip += 2;
+                    Status::Running
+                } else {
+                    Status::WaitForInput
+                }
+            }
+            Opcode::Output => {
+                let param = memory[state.ip + 1];
+                let output = mode(0)?.read(
+                    ParamArgs {
+                        param,
+                        relative_base,
+                    },
+                    memory,
+                );
+                output_buffer.write(output);
+                state.ip += 2;
+                Status::WroteOutput
+            }
+            Opcode::JumpIfTrue => {
+                let cond_param = memory[state.ip + 1];
+                let target_param = memory[state.ip + 2];
+                let cond = mode(0)?.read(
+                    ParamArgs {
+                        param: cond_param,
+                        relative_base,
+                    },
+                    memory,
+                );
+                if cond != 0 {
+                    let target = mode(1)?.read(
+                        ParamArgs {
+                            param: target_param,
+                            relative_base,
+                        },
+                        memory,
+                    );
+                    state.ip = target as usize;
+                    Status::Running
+                } else {
+                    state.ip += 3;
+                    Status::Running
+                }
+            }
+            Opcode::JumpIfFalse => {
+                let cond_param = memory[state.ip + 1];
+                let target_param = memory[state.ip + 2];
+                let cond = mode(0)?.read(
+                    ParamArgs {
+                        param: cond_param,
+                        relative_base,
+                    },
+                    memory,
+                );
+                if cond == 0 {
+                    let target = mode(1)?.read(
+                        ParamArgs {
+                            param: target_param,
+                            relative_base,
+                        },
+                        memory,
+                    );
+                    state.ip = target as usize;
+                    Status::Running
+                } else {
+                    state.ip += 3;
+                    Status::Running
+                }
+            }
+            Opcode::LessThan => {
+                let lhs_param = memory[state.ip + 1];
+                let rhs_param = memory[state.ip + 2];
+                let dst_param = memory[state.ip + 3];
+                let lhs = mode(0)?.read(
+                    ParamArgs {
+                        param: lhs_param,
+                        relative_base,
+                    },
+                    memory,
+                );
+                let rhs = mode(1)?.read(
+                    ParamArgs {
+                        param: rhs_param,
+                        relative_base,
+                    },
+                    memory,
+                );
+                let value = (lhs < rhs) as i128;
+                mode(2)?.write(
+                    ParamArgs {
+                        param: dst_param,
+                        relative_base,
+                    },
+                    value,
+                    memory,
+                );
+                state.ip += 4;
+                Status::Running
+            }
+            Opcode::Equals => {
+                let lhs_param = memory[state.ip + 1];
+                let rhs_param = memory[state.ip + 2];
+                let dst_param = memory[state.ip + 3];
+                let lhs = mode(0)?.read(
+                    ParamArgs {
+                        param: lhs_param,
+                        relative_base,
+                    },
+                    memory,
+                );
+                let rhs = mode(1)?.read(
+                    ParamArgs {
+                        param: rhs_param,
+                        relative_base,
+                    },
+                    memory,
+                );
+                let value = (lhs == rhs) as i128;
+                mode(2)?.write(
+                    ParamArgs {
+                        param: dst_param,
+                        relative_base,
+                    },
+                    value,
+                    memory,
+                );
+                state.ip += 4;
+                Status::Running
+            }
+            Opcode::AdjustRelativeBase => {
+                let param = memory[state.ip + 1];
+                let adjust_by = mode(0)?.read(
+                    ParamArgs {
+                        param,
+                        relative_base,
+                    },
+                    memory,
+                );
+                state.relative_base += adjust_by;
+                state.ip += 2;
+                Status::Running
+            }
+            Opcode::Halt => Status::Halt,
+            Opcode::Extension(_) => unreachable!("extension opcodes are run by IntcodeComputer"),
+        })
+    }
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Param {
+    Read,
+    Write,
+}
+
+pub struct Context<'a> {
+    pub memory: &'a mut Memory,
+    pub state: &'a mut State,
+    pub input_buffer: &'a mut IoBuffer,
+    pub output_buffer: &'a mut IoBuffer,
+    param_modes: &'a ParamModes,
+    jump_target: Option<usize>,
+}
+
+impl<'a> Context<'a> {
+    fn args(&self, n: usize) -> ParamArgs {
+        ParamArgs {
+            param: self.memory[self.state.ip + 1 + n],
+            relative_base: self.state.relative_base,
+        }
+    }
+    // Modes are checked before an extension runs
+    fn mode(&self, n: usize) -> ParamMode {
+        self.param_modes
+            .nth(n as u32)
+            .expect("parameter modes are checked before running an extension")
+    }
+    pub fn read(&mut self, n: usize) -> i128 {
+        let args = self.args(n);
+        self.mode(n).read(args, self.memory)
+    }
+    pub fn write(&mut self, n: usize, value: i128) {
+        let args = self.args(n);
+        self.mode(n).write(args, value, self.memory)
+    }
+    pub fn jump(&mut self, target: usize) {
+        self.jump_target = Some(target);
+    }
+}
+
+type Handler = Box<dyn FnMut(&mut Context) -> Status>;
+
+pub struct Extension {
+    pub mnemonic: String,
+    pub params: Vec<Param>,
+    handler: Option<Handler>,
+}
+
+#[derive(Default)]
+pub struct Extensions {
+    opcodes: HashMap<i128, Extension>,
+}
+
+impl Extensions {
+    fn insert(&mut self, opcode: i128, mnemonic: &str, params: &[Param], handler: Option<Handler>) {
+        assert!(
+            (1..100).contains(&opcode) && Opcode::builtin(opcode).is_none(),
+            "cannot register opcode {}",
+            opcode
+        );
+        self.opcodes.insert(
+            opcode,
+            Extension {
+                mnemonic: mnemonic.to_string(),
+                params: params.to_vec(),
+                handler,
+            },
+        );
+    }
+    pub fn register<F>(&mut self, opcode: i128, mnemonic: &str, params: &[Param], handler: F)
+    where
+        F: 'static + FnMut(&mut Context) -> Status,
+    {
+        self.insert(opcode, mnemonic, params, Some(Box::new(handler)));
+    }
+    pub fn declare(&mut self, opcode: i128, mnemonic: &str, params: &[Param]) {
+        self.insert(opcode, mnemonic, params, None);
+    }
+    pub fn get(&self, opcode: i128) -> Option<&Extension> {
+        self.opcodes.get(&opcode)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    UnknownOpcode { ip: usize, opcode: i128 },
+    UnknownParamMode { ip: usize, mode: i128 },
+    MissingHandler { ip: usize, opcode: i128 },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownOpcode { ip, opcode } => {
+                write!(f, "unexpected opcode {} at ip {}", opcode, ip)
+            }
+            Self::UnknownParamMode { ip, mode } => {
+                write!(f, "unexpected parameter mode {} at ip {}", mode, ip)
+            }
+            Self::MissingHandler { ip, opcode } => {
+                write!(f, "opcode {} at ip {} has no handler", opcode, ip)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+type Hook = Box<dyn FnMut(usize, i128) -> i128>;
+
+struct Region {
+    range: Range<usize>,
+    on_read: Option<Hook>,
+    on_write: Option<Hook>,
+}
+
+pub struct Memory {
+    cells: Vec<i128>,
+    regions: Vec<Region>,
+    hooked_span: Range<usize>,
+}
+
+impl Memory {
+    fn new(cells: Vec<i128>) -> Self {
+        Self {
+            cells,
+            regions: Vec::new(),
+            hooked_span: 0..0,
+        }
+    }
+    pub fn read(&mut self, address: usize) -> i128 {
+        let value = self.cells[address];
+        if address < self.hooked_span.start || address >= self.hooked_span.end {
+            return value;
+        }
+        self.regions
+            .iter_mut()
+            .filter(|region| region.range.contains(&address))
+            .filter_map(|region| region.on_read.as_mut())
+            .fold(value, |value, hook| hook(address, value))
+    }
+    pub fn write(&mut self, address: usize, value: i128) {
+        let value = if address < self.hooked_span.start || address >= self.hooked_span.end {
+            value
+        } else {
+            self.regions
+                .iter_mut()
+                .filter(|region| region.range.contains(&address))
+                .filter_map(|region| region.on_write.as_mut())
+                .fold(value, |value, hook| hook(address, value))
+        };
+        self.cells[address] = value;
+    }
+    fn map(&mut self, range: Range<usize>, on_read: Option<Hook>, on_write: Option<Hook>) {
+        assert!(range.end <= self.cells.len(), "region outside memory");
+        self.hooked_span = if self.regions.is_empty() {
+            range.clone()
+        } else {
+            self.hooked_span.start.min(range.start)..self.hooked_span.end.max(range.end)
+        };
+        self.regions.push(Region {
+            range,
+            on_read,
+            on_write,
+        });
+    }
+    pub fn on_read<F>(&mut self, range: Range<usize>, hook: F)
+    where
+        F: 'static + FnMut(usize, i128) -> i128,
+    {
+        self.map(range, Some(Box::new(hook)), None);
+    }
+    pub fn on_write<F>(&mut self, range: Range<usize>, hook: F)
+    where
+        F: 'static + FnMut(usize, i128) -> i128,
+    {
+        self.map(range, None, Some(Box::new(hook)));
+    }
+    pub fn clear_hooks(&mut self) {
+        self.regions.clear();
+        self.hooked_span = 0..0;
+    }
+}
+
+impl Deref for Memory {
+    type Target = [i128];
+    fn deref(&self) -> &[i128] {
+        &self.cells
+    }
+}
+
+impl DerefMut for Memory {
+    fn deref_mut(&mut self) -> &mut [i128] {
+        &mut self.cells
+    }
+}
+
+pub struct IntcodeComputer {
+    pub memory: Memory,
+    pub state: State,
+    extensions: Extensions,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum StopStatus {
+    WaitForInput,
+    WroteOutput,
+    Halt,
+}
+
+impl IntcodeComputer {
+    pub fn new(program: &[i128]) -> Self {
+        Self::with_extensions(program, Extensions::default())
+    }
+    pub fn with_extensions(program: &[i128], extensions: Extensions) -> Self {
+        let mut memory = vec![0; 1 << 16];
+        memory[0..program.len()].copy_from_slice(program);
+        Self {
+            memory: Memory::new(memory),
+            state: State::default(),
+            extensions,
+        }
+    }
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+    fn run_extension(
+        &mut self,
+        opcode: i128,
+        param_modes: &ParamModes,
+        input_buffer: &mut IoBuffer,
+        output_buffer: &mut IoBuffer,
+    ) -> Result<Status, Error> {
+        let ip = self.state.ip;
+        let extension = self.extensions.opcodes.get_mut(&opcode).unwrap();
+        let num_params = extension.params.len();
+        if let Some(n) = (0..num_params as u32).find(|&n| param_modes.nth(n).is_none()) {
+            return Err(Error::UnknownParamMode {
+                ip,
+                mode: param_modes.nth_code(n),
+            });
+        }
+        let handler = extension
+            .handler
+            .as_mut()
+            .ok_or(Error::MissingHandler { ip, opcode })?;
+        let mut context = Context {
+            memory: &mut self.memory,
+            state: &mut self.state,
+            input_buffer,
+            output_buffer,
+            param_modes,
+            jump_target: None,
+        };
+        let status = handler(&mut context);
+        let jump_target = context.jump_target;
+        match status {
+            Status::Running | Status::WroteOutput => {
+                self.state.ip = jump_target.unwrap_or(ip + 1 + num_params)
+            }
+            Status::WaitForInput | Status::Halt => (),
+        }
+        Ok(status)
+    }
+    pub fn step(
+        &mut self,
+        input_buffer: &mut IoBuffer,
+        output_buffer: &mut IoBuffer,
+    ) -> Result<Option<StopStatus>, Error> {
+        let ip = self.state.ip;
+        let encoded = self.memory[ip];
+        let instruction = Instruction::decode(encoded, &self.extensions).ok_or(
+            Error::UnknownOpcode {
+                ip,
+                opcode: encoded % 100,
+            },
+        )?;
+        let status = match instruction.opcode {
+            Opcode::Extension(opcode) => self.run_extension(
+                opcode,
+                &instruction.param_modes,
+                input_buffer,
+                output_buffer,
+            )?,
+            _ => instruction.run(
+                &mut self.memory,
+                &mut self.state,
+                input_buffer,
+                output_buffer,
+            )?,
+        };
+        Ok(match status {
+            Status::Running => None,
+            Status::Halt => Some(StopStatus::Halt),
+            Status::WaitForInput => Some(StopStatus::WaitForInput),
+            Status::WroteOutput => Some(StopStatus::WroteOutput),
+        })
+    }
+    pub fn run(
+        &mut self,
+        input_buffer: &mut IoBuffer,
+        output_buffer: &mut IoBuffer,
+    ) -> Result<StopStatus, Error> {
+        loop {
+            if let Some(stop_status) = self.step(input_buffer, output_buffer)? {
+                return Ok(stop_status);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn unknown_opcode_is_an_error() {
+        let mut computer = IntcodeComputer::new(&[1101, 1, 2, 5, 42, 0]);
+        let mut input_buffer = IoBuffer::default();
+        let mut output_buffer = IoBuffer::default();
+        assert_eq!(
+            computer.run(&mut input_buffer, &mut output_buffer),
+            Err(Error::UnknownOpcode { ip: 4, opcode: 42 })
+        );
+    }
+
+    #[test]
+    fn unknown_param_mode_is_an_error() {
+        let mut computer = IntcodeComputer::new(&[1101, 1, 2, 7, 30001, 0, 0, 0]);
+        let mut input_buffer = IoBuffer::default();
+        let mut output_buffer = IoBuffer::default();
+        assert_eq!(
+            computer.run(&mut input_buffer, &mut output_buffer),
+            Err(Error::UnknownParamMode { ip: 4, mode: 3 })
+        );
+        let mut extensions = Extensions::default();
+        extensions.register(42, "DBG", &[Param::Read], |_| Status::Running);
+        let mut computer = IntcodeComputer::with_extensions(&[542, 0, 99], extensions);
+        assert_eq!(
+            computer.run(&mut input_buffer, &mut output_buffer),
+            Err(Error::UnknownParamMode { ip: 0, mode: 5 })
+        );
+    }
+
+    #[test]
+    fn extension_opcodes() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut extensions = Extensions::default();
+        let debug_log = log.clone();
+        extensions.register(42, "DBG", &[Param::Read], move |context| {
+            debug_log.borrow_mut().push(context.read(0));
+            Status::Running
+        });
+        let mut seed = 7;
+        extensions.register(43, "RND", &[Param::Write], move |context| {
+            seed = (seed * 1103515245 + 12345) % (1 << 31);
+            context.write(0, seed % 100);
+            Status::Running
+        });
+        let program = [142, 5, 42, 0, 43, 9, 4, 9, 99, 0];
+        let mut computer = IntcodeComputer::with_extensions(&program, extensions);
+        let mut input_buffer = IoBuffer::default();
+        let mut output_buffer = IoBuffer::default();
+        assert_eq!(
+            computer.run(&mut input_buffer, &mut output_buffer),
+            Ok(StopStatus::WroteOutput)
+        );
+        assert_eq!(*log.borrow(), vec![5, 142]);
+        assert_eq!(output_buffer.read(), Some(computer.memory[9]));
+        assert_eq!(
+            computer.run(&mut input_buffer, &mut output_buffer),
+            Ok(StopStatus::Halt)
+        );
+    }
+
+    #[test]
+    fn extension_can_jump() {
+        let mut extensions = Extensions::default();
+        extensions.register(50, "SKIP", &[Param::Read], |context| {
+            let target = context.state.ip + 2 + context.read(0) as usize;
+            context.jump(target);
+            Status::Running
+        });
+        let program = [150, 2, 104, 1, 104, 2, 99];
+        let mut computer = IntcodeComputer::with_extensions(&program, extensions);
+        let mut input_buffer = IoBuffer::default();
+        let mut output_buffer = IoBuffer::default();
+        computer.run(&mut input_buffer, &mut output_buffer).unwrap();
+        assert_eq!(output_buffer.read(), Some(2));
+    }
+
+    #[test]
+    fn write_hooks_observe_memory() {
+        let score = Rc::new(RefCell::new(Vec::new()));
+        let program = [1001, 12, 5, 12, 1007, 12, 20, 13, 1005, 13, 0, 99, 0, 0];
+        let mut computer = IntcodeComputer::new(&program);
+        let observed = score.clone();
+        computer.memory.on_write(12..13, move |_, value| {
+            observed.borrow_mut().push(value);
+            value
+        });
+        let mut input_buffer = IoBuffer::default();
+        let mut output_buffer = IoBuffer::default();
+        assert_eq!(
+            computer.run(&mut input_buffer, &mut output_buffer),
+            Ok(StopStatus::Halt)
+        );
+        assert_eq!(*score.borrow(), vec![5, 10, 15, 20]);
+    }
+
+    #[test]
+    fn read_hooks_inject_values() {
+        let program = [4, 7, 4, 8, 4, 9, 99, 10, 20, 30];
+        let mut computer = IntcodeComputer::new(&program);
+        computer.memory.on_read(8..9, |address, _| address as i128 * 100);
+        let mut input_buffer = IoBuffer::default();
+        let mut output_buffer = IoBuffer::default();
+        while computer.run(&mut input_buffer, &mut output_buffer) != Ok(StopStatus::Halt) {}
+        assert_eq!(output_buffer.drain().collect::<Vec<_>>(), vec![10, 800, 30]);
+        assert_eq!(computer.memory[8], 20);
+    }
+
+    #[test]
+    fn declared_opcode_without_handler() {
+        let mut extensions = Extensions::default();
+        extensions.declare(42, "DBG", &[Param::Read]);
+        let mut computer = IntcodeComputer::with_extensions(&[42, 0, 99], extensions);
+        let mut input_buffer = IoBuffer::default();
+        let mut output_buffer = IoBuffer::default();
+        assert_eq!(
+            computer.run(&mut input_buffer, &mut output_buffer),
+            Err(Error::MissingHandler { ip: 0, opcode: 42 })
+        );
+    }
+}
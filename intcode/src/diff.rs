@@ -0,0 +1,603 @@
+use crate::computer::Extensions;
+use crate::disasm;
+use crate::variants;
+use std::cell::Cell;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
+
+/// The name of the variant every other interpreter is checked against
+pub const REFERENCE: &str = "intcode";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    Running,
+    Output(i128),
+    WaitForInput,
+    Halt,
+}
+
+pub trait Machine {
+    fn ip(&self) -> usize;
+    fn relative_base(&self) -> i128 {
+        0
+    }
+    fn peek(&self, address: usize) -> Option<i128>;
+    fn memory_len(&self) -> usize;
+    fn step(&mut self) -> Step;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Word {
+    Usize,
+    I32,
+    I128,
+}
+
+impl Word {
+    fn contains(self, value: i128) -> bool {
+        match self {
+            Self::Usize => value >= 0 && value <= usize::MAX as i128,
+            Self::I32 => value >= i32::MIN as i128 && value <= i32::MAX as i128,
+            Self::I128 => true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Dialect {
+    pub opcodes: &'static [i128],
+    pub modes: &'static [i128],
+    pub word: Word,
+}
+
+impl Dialect {
+    pub const ADD_MUL: Self = Self {
+        opcodes: &[1, 2, 99],
+        modes: &[0],
+        word: Word::Usize,
+    };
+    pub const IO: Self = Self {
+        opcodes: &[1, 2, 3, 4, 99],
+        modes: &[0, 1],
+        word: Word::I32,
+    };
+    pub const JUMPS: Self = Self {
+        opcodes: &[1, 2, 3, 4, 5, 6, 7, 8, 99],
+        modes: &[0, 1],
+        word: Word::I32,
+    };
+    pub const RELATIVE: Self = Self {
+        opcodes: &[1, 2, 3, 4, 5, 6, 7, 8, 9, 99],
+        modes: &[0, 1, 2],
+        word: Word::I128,
+    };
+    fn has_relative_base(&self) -> bool {
+        self.modes.contains(&2)
+    }
+}
+
+#[derive(Clone)]
+pub struct Variant {
+    pub name: &'static str,
+    pub dialect: Dialect,
+    pub new: fn(program: &[i128], inputs: &[i128]) -> Box<dyn Machine>,
+}
+
+fn param_count(opcode: i128) -> usize {
+    match opcode {
+        1 | 2 | 7 | 8 => 3,
+        5 | 6 => 2,
+        3 | 4 | 9 => 1,
+        _ => 0,
+    }
+}
+
+fn write_param(opcode: i128) -> Option<usize> {
+    match opcode {
+        1 | 2 | 7 | 8 => Some(2),
+        3 => Some(0),
+        _ => None,
+    }
+}
+
+fn param_mode(encoded: i128, n: usize) -> i128 {
+    (encoded / 10_i128.pow(n as u32 + 2)) % 10
+}
+
+#[derive(Clone, Copy, Debug)]
+struct RawInstruction {
+    ip: usize,
+    words: [i128; 4],
+}
+
+impl RawInstruction {
+    fn fetch(machine: &dyn Machine) -> Self {
+        let ip = machine.ip();
+        let mut words = [0; 4];
+        for (i, word) in words.iter_mut().enumerate() {
+            *word = machine.peek(ip + i).unwrap_or(0);
+        }
+        Self { ip, words }
+    }
+    fn opcode(&self) -> i128 {
+        self.words[0] % 100
+    }
+    fn modes(&self) -> impl Iterator<Item = i128> + '_ {
+        (0..param_count(self.opcode())).map(move |n| param_mode(self.words[0], n))
+    }
+    fn write_address(&self, relative_base: i128) -> Option<usize> {
+        let n = write_param(self.opcode())?;
+        let param = self.words[n + 1];
+        let address = match param_mode(self.words[0], n) {
+            0 => param,
+            2 => param + relative_base,
+            _ => return None,
+        };
+        if address < 0 {
+            None
+        } else {
+            Some(address as usize)
+        }
+    }
+}
+
+impl fmt::Display for RawInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let line = disasm::decode(&self.words, self.ip, &Extensions::default());
+        write!(f, "{}", line)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Stop {
+    Halt,
+    WaitForInput,
+    Panic(String),
+    StepLimit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub step: usize,
+    pub instruction: String,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Agrees,
+    Unsupported(String),
+    Diverged(Divergence),
+}
+
+pub struct Report {
+    pub reference: &'static str,
+    pub stop: Stop,
+    pub steps: usize,
+    pub outputs: Vec<i128>,
+    pub verdicts: Vec<(&'static str, Verdict)>,
+}
+
+impl Report {
+    pub fn diverged(&self) -> bool {
+        self.verdicts
+            .iter()
+            .any(|(_, verdict)| matches!(verdict, Verdict::Diverged(_)))
+    }
+}
+
+const MAX_DISPLAYED_OUTPUTS: usize = 16;
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "reference {}: {:?} after {} steps",
+            self.reference, self.stop, self.steps
+        )?;
+        if self.outputs.len() <= MAX_DISPLAYED_OUTPUTS {
+            writeln!(f, "outputs: {:?}", self.outputs)?;
+        } else {
+            writeln!(
+                f,
+                "outputs: {} values ending {:?}",
+                self.outputs.len(),
+                &self.outputs[self.outputs.len() - MAX_DISPLAYED_OUTPUTS..]
+            )?;
+        }
+        for (name, verdict) in &self.verdicts {
+            match verdict {
+                Verdict::Agrees => writeln!(f, "{}: agrees", name)?,
+                Verdict::Unsupported(reason) => writeln!(f, "{}: skipped ({})", name, reason)?,
+                Verdict::Diverged(Divergence {
+                    step,
+                    instruction,
+                    detail,
+                }) => writeln!(
+                    f,
+                    "{}: DIVERGED at step {} ({}): {}",
+                    name, step, instruction, detail
+                )?,
+            }
+        }
+        Ok(())
+    }
+}
+
+thread_local! {
+    static IN_GUARDED_STEP: Cell<bool> = const { Cell::new(false) };
+}
+
+static INSTALL_PANIC_HOOK: Once = Once::new();
+
+// Panics inside a variant are reported as divergences, so keep them quiet, but leave the
+// previous hook in place for everything else
+fn install_panic_hook() {
+    INSTALL_PANIC_HOOK.call_once(|| {
+        let previous_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if !IN_GUARDED_STEP.with(Cell::get) {
+                previous_hook(info);
+            }
+        }));
+    });
+}
+
+fn guarded_step(machine: &mut dyn Machine) -> Result<Step, String> {
+    IN_GUARDED_STEP.with(|in_guarded_step| in_guarded_step.set(true));
+    let result = panic::catch_unwind(AssertUnwindSafe(|| machine.step()));
+    IN_GUARDED_STEP.with(|in_guarded_step| in_guarded_step.set(false));
+    result.map_err(|payload| {
+        if let Some(s) = payload.downcast_ref::<&str>() {
+            s.to_string()
+        } else if let Some(s) = payload.downcast_ref::<String>() {
+            s.clone()
+        } else {
+            "panic".to_string()
+        }
+    })
+}
+
+struct Candidate<'a> {
+    variant: &'a Variant,
+    machine: Option<Box<dyn Machine>>,
+    verdict: Option<Verdict>,
+}
+
+impl<'a> Candidate<'a> {
+    fn new(variant: &'a Variant, program: &[i128], inputs: &[i128]) -> Self {
+        let word = variant.dialect.word;
+        let unrepresentable = program
+            .iter()
+            .chain(inputs.iter())
+            .find(|&&value| !word.contains(value));
+        if let Some(value) = unrepresentable {
+            Self {
+                variant,
+                machine: None,
+                verdict: Some(Verdict::Unsupported(format!(
+                    "value {} does not fit in {:?}",
+                    value, word
+                ))),
+            }
+        } else {
+            Self {
+                variant,
+                machine: Some((variant.new)(program, inputs)),
+                verdict: None,
+            }
+        }
+    }
+    fn retire(&mut self, verdict: Verdict) {
+        self.machine = None;
+        self.verdict = Some(verdict);
+    }
+}
+
+fn diverged(step: usize, instruction: &RawInstruction, detail: String) -> Verdict {
+    Verdict::Diverged(Divergence {
+        step,
+        instruction: instruction.to_string(),
+        detail,
+    })
+}
+
+pub fn compare_variants(
+    reference: &Variant,
+    candidates: &[Variant],
+    program: &[i128],
+    inputs: &[i128],
+    max_steps: usize,
+) -> Report {
+    install_panic_hook();
+    let mut reference_machine = (reference.new)(program, inputs);
+    let mut candidates = candidates
+        .iter()
+        .map(|variant| Candidate::new(variant, program, inputs))
+        .collect::<Vec<_>>();
+    let mut outputs = Vec::new();
+    let mut steps = 0;
+    let stop = loop {
+        if steps == max_steps {
+            break Stop::StepLimit;
+        }
+        let instruction = RawInstruction::fetch(&*reference_machine);
+        let opcode = instruction.opcode();
+        let write_address = instruction.write_address(reference_machine.relative_base());
+        for candidate in candidates.iter_mut() {
+            if candidate.machine.is_none() {
+                continue;
+            }
+            let dialect = &candidate.variant.dialect;
+            if !dialect.opcodes.contains(&opcode) {
+                candidate.retire(Verdict::Unsupported(format!(
+                    "opcode {} at step {}",
+                    opcode, steps
+                )));
+            } else if let Some(mode) = instruction.modes().find(|m| !dialect.modes.contains(m)) {
+                candidate.retire(Verdict::Unsupported(format!(
+                    "parameter mode {} at step {}",
+                    mode, steps
+                )));
+            }
+        }
+        let reference_step = guarded_step(&mut *reference_machine);
+        let written = write_address.and_then(|address| reference_machine.peek(address));
+        for candidate in candidates.iter_mut() {
+            let machine = match candidate.machine.as_mut() {
+                Some(machine) => machine,
+                None => continue,
+            };
+            let dialect = &candidate.variant.dialect;
+            let produced = match reference_step {
+                Ok(Step::Output(value)) => Some(value),
+                _ => written,
+            };
+            if let Some(value) = produced.filter(|&value| !dialect.word.contains(value)) {
+                candidate.retire(Verdict::Unsupported(format!(
+                    "value {} does not fit in {:?} at step {}",
+                    value, dialect.word, steps
+                )));
+                continue;
+            }
+            if let Some(address) = write_address.filter(|&a| a >= machine.memory_len()) {
+                candidate.retire(Verdict::Unsupported(format!(
+                    "write to address {} beyond memory at step {}",
+                    address, steps
+                )));
+                continue;
+            }
+            let candidate_step = guarded_step(&mut **machine);
+            let detail = match (&reference_step, &candidate_step) {
+                (Ok(expected), Ok(actual)) if expected != actual => {
+                    Some(format!("expected {:?}, got {:?}", expected, actual))
+                }
+                (Ok(expected), Err(message)) => {
+                    Some(format!("expected {:?}, panicked: {}", expected, message))
+                }
+                (Err(message), Ok(actual)) => Some(format!(
+                    "expected panic ({}), got {:?}",
+                    message, actual
+                )),
+                _ => None,
+            };
+            let detail = detail.or_else(|| {
+                if reference_step.is_err() {
+                    None
+                } else if machine.ip() != reference_machine.ip() {
+                    Some(format!(
+                        "expected ip {}, got {}",
+                        reference_machine.ip(),
+                        machine.ip()
+                    ))
+                } else if dialect.has_relative_base()
+                    && machine.relative_base() != reference_machine.relative_base()
+                {
+                    Some(format!(
+                        "expected relative base {}, got {}",
+                        reference_machine.relative_base(),
+                        machine.relative_base()
+                    ))
+                } else {
+                    write_address.and_then(|address| {
+                        let actual = machine.peek(address);
+                        if actual != written {
+                            Some(format!(
+                                "expected [{}] = {:?}, got {:?}",
+                                address, written, actual
+                            ))
+                        } else {
+                            None
+                        }
+                    })
+                }
+            });
+            if let Some(detail) = detail {
+                candidate.retire(diverged(steps, &instruction, detail));
+            }
+        }
+        steps += 1;
+        match reference_step {
+            Ok(Step::Running) => (),
+            Ok(Step::Output(value)) => outputs.push(value),
+            Ok(Step::WaitForInput) => break Stop::WaitForInput,
+            Ok(Step::Halt) => break Stop::Halt,
+            Err(message) => break Stop::Panic(message),
+        }
+    };
+    let last_instruction = RawInstruction::fetch(&*reference_machine);
+    let verdicts = candidates
+        .into_iter()
+        .map(|candidate| {
+            let verdict = match (candidate.verdict, candidate.machine) {
+                (Some(verdict), _) => verdict,
+                (None, None) => unreachable!(),
+                (None, Some(machine)) => {
+                    let len = machine.memory_len().max(reference_machine.memory_len());
+                    (0..len)
+                        .find_map(|address| {
+                            let expected = reference_machine.peek(address).unwrap_or(0);
+                            let actual = machine.peek(address).unwrap_or(0);
+                            if expected != actual {
+                                Some(diverged(
+                                    steps,
+                                    &last_instruction,
+                                    format!(
+                                        "final memory differs at [{}]: expected {}, got {}",
+                                        address, expected, actual
+                                    ),
+                                ))
+                            } else {
+                                None
+                            }
+                        })
+                        .unwrap_or(Verdict::Agrees)
+                }
+            };
+            (candidate.variant.name, verdict)
+        })
+        .collect();
+    Report {
+        reference: reference.name,
+        stop,
+        steps,
+        outputs,
+        verdicts,
+    }
+}
+
+pub fn compare(program: &[i128], inputs: &[i128], max_steps: usize) -> Report {
+    let candidates = variants::ALL
+        .iter()
+        .filter(|variant| variant.name != REFERENCE)
+        .cloned()
+        .collect::<Vec<_>>();
+    compare_variants(reference(), &candidates, program, inputs, max_steps)
+}
+
+pub fn reference() -> &'static Variant {
+    variants::ALL
+        .iter()
+        .find(|variant| variant.name == REFERENCE)
+        .expect("no reference variant")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(s: &str) -> Vec<i128> {
+        s.split(',').map(|s| s.trim().parse().unwrap()).collect()
+    }
+
+    fn verdict<'a>(report: &'a Report, name: &str) -> &'a Verdict {
+        &report
+            .verdicts
+            .iter()
+            .find(|(n, _)| *n == name)
+            .unwrap()
+            .1
+    }
+
+    #[test]
+    fn reference_is_the_library() {
+        let report = compare(&parse("99"), &[], 10);
+        assert_eq!(report.reference, REFERENCE);
+        assert!(report.verdicts.iter().all(|(name, _)| *name != REFERENCE));
+        assert!(report.verdicts.iter().any(|(name, _)| *name == "day25-1"));
+    }
+
+    #[test]
+    fn add_mul_example() {
+        let program = parse("1,9,10,3,2,3,11,0,99,30,40,50");
+        let report = compare(&program, &[], 1000);
+        assert_eq!(report.stop, Stop::Halt);
+        assert!(!report.diverged(), "{}", report);
+        assert_eq!(verdict(&report, "day2-1"), &Verdict::Agrees);
+        assert_eq!(verdict(&report, "day9-2"), &Verdict::Agrees);
+    }
+
+    #[test]
+    fn compare_to_eight_example() {
+        let program = parse("3,9,8,9,10,9,4,9,99,-1,8");
+        for &(input, expected) in &[(8, 1), (7, 0)] {
+            let report = compare(&program, &[input], 1000);
+            assert_eq!(report.outputs, vec![expected]);
+            assert!(!report.diverged(), "{}", report);
+            assert_eq!(verdict(&report, "day5-2"), &Verdict::Agrees);
+            assert_eq!(verdict(&report, "day7-2"), &Verdict::Agrees);
+            assert!(matches!(
+                verdict(&report, "day5-1"),
+                Verdict::Unsupported(_)
+            ));
+        }
+    }
+
+    #[test]
+    fn quine_example() {
+        let program = parse("109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99");
+        let report = compare(&program, &[], 10000);
+        assert_eq!(report.outputs, program);
+        assert!(!report.diverged(), "{}", report);
+        assert!(matches!(
+            verdict(&report, "day7-2"),
+            Verdict::Unsupported(_)
+        ));
+    }
+
+    struct OffByOne {
+        memory: Vec<i128>,
+        ip: usize,
+    }
+
+    impl Machine for OffByOne {
+        fn ip(&self) -> usize {
+            self.ip
+        }
+        fn peek(&self, address: usize) -> Option<i128> {
+            self.memory.get(address).cloned()
+        }
+        fn memory_len(&self) -> usize {
+            self.memory.len()
+        }
+        fn step(&mut self) -> Step {
+            let m = &mut self.memory;
+            let ip = self.ip;
+            match m[ip] {
+                1 => {
+                    let (lhs, rhs, dst) = (m[ip + 1], m[ip + 2], m[ip + 3]);
+                    m[dst as usize] = m[lhs as usize] + m[rhs as usize] + 1;
+                    self.ip += 4;
+                    Step::Running
+                }
+                99 => Step::Halt,
+                _ => panic!(),
+            }
+        }
+    }
+
+    #[test]
+    fn reports_first_diverging_instruction() {
+        let broken = Variant {
+            name: "broken",
+            dialect: Dialect::ADD_MUL,
+            new: |program, _| {
+                Box::new(OffByOne {
+                    memory: program.to_vec(),
+                    ip: 0,
+                })
+            },
+        };
+        let program = parse("1,0,0,0,99");
+        let report = compare_variants(reference(), &[broken], &program, &[], 1000);
+        assert_eq!(
+            verdict(&report, "broken"),
+            &Verdict::Diverged(Divergence {
+                step: 0,
+                instruction: "0: ADD [0], [0], [0]".to_string(),
+                detail: "expected [0] = Some(2), got Some(3)".to_string(),
+            })
+        );
+    }
+}
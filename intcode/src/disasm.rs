@@ -0,0 +1,157 @@
+use crate::computer::{Extensions, Opcode, Param, ParamMode};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    Positional(i128),
+    Immediate(i128),
+    Relative(i128),
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Positional(address) => write!(f, "[{}]", address),
+            Self::Immediate(value) => write!(f, "#{}", value),
+            Self::Relative(offset) => write!(f, "[rb{:+}]", offset),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Line {
+    Instruction {
+        address: usize,
+        mnemonic: String,
+        operands: Vec<Operand>,
+    },
+    Data {
+        address: usize,
+        value: i128,
+    },
+}
+
+impl Line {
+    pub fn address(&self) -> usize {
+        match self {
+            Self::Instruction { address, .. } | Self::Data { address, .. } => *address,
+        }
+    }
+    pub fn size(&self) -> usize {
+        match self {
+            Self::Instruction { operands, .. } => 1 + operands.len(),
+            Self::Data { .. } => 1,
+        }
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Instruction {
+                address,
+                mnemonic,
+                operands,
+            } => {
+                write!(f, "{}: {}", address, mnemonic)?;
+                for (i, operand) in operands.iter().enumerate() {
+                    let sep = if i == 0 { " " } else { ", " };
+                    write!(f, "{}{}", sep, operand)?;
+                }
+                Ok(())
+            }
+            Self::Data { address, value } => write!(f, "{}: DATA {}", address, value),
+        }
+    }
+}
+
+fn decode_instruction(words: &[i128], address: usize, extensions: &Extensions) -> Option<Line> {
+    let encoded = *words.first()?;
+    if encoded < 0 {
+        return None;
+    }
+    let opcode = Opcode::from_i128(encoded % 100, extensions)?;
+    let params = opcode.params(extensions);
+    if encoded / 100 / 10_i128.pow(params.len() as u32) != 0 || words.len() <= params.len() {
+        return None;
+    }
+    let mut operands = Vec::new();
+    for (n, &param) in params.iter().enumerate() {
+        let value = words[n + 1];
+        let operand = match ParamMode::from_i128((encoded / 10_i128.pow(n as u32 + 2)) % 10)? {
+            ParamMode::Positional => Operand::Positional(value),
+            ParamMode::Immediate if param == Param::Write => return None,
+            ParamMode::Immediate => Operand::Immediate(value),
+            ParamMode::Relative => Operand::Relative(value),
+        };
+        operands.push(operand);
+    }
+    Some(Line::Instruction {
+        address,
+        mnemonic: opcode.mnemonic(extensions).to_string(),
+        operands,
+    })
+}
+
+pub fn decode(words: &[i128], address: usize, extensions: &Extensions) -> Line {
+    decode_instruction(words, address, extensions).unwrap_or(Line::Data {
+        address,
+        value: words.first().cloned().unwrap_or(0),
+    })
+}
+
+pub fn disassemble(program: &[i128], extensions: &Extensions) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut address = 0;
+    while address < program.len() {
+        let line = decode(&program[address..], address, extensions);
+        address += line.size();
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn listing(program: &[i128], extensions: &Extensions) -> Vec<String> {
+        disassemble(program, extensions)
+            .iter()
+            .map(|line| line.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn builtin_instructions() {
+        let program = [109, 1, 204, -1, 1001, 100, 1, 100, 99, 12345];
+        assert_eq!(
+            listing(&program, &Extensions::default()),
+            vec![
+                "0: ARB #1",
+                "2: OUT [rb-1]",
+                "4: ADD [100], #1, [100]",
+                "8: HALT",
+                "9: DATA 12345",
+            ]
+        );
+    }
+
+    #[test]
+    fn registered_mnemonics() {
+        let mut extensions = Extensions::default();
+        extensions.declare(42, "DBG", &[Param::Read]);
+        extensions.declare(43, "RND", &[Param::Write]);
+        let program = [142, 5, 43, 9, 143, 9, 44, 99];
+        assert_eq!(
+            listing(&program, &extensions),
+            vec![
+                "0: DBG #5",
+                "2: RND [9]",
+                "4: DATA 143",
+                "5: ARB [44]",
+                "7: HALT",
+            ]
+        );
+    }
+}
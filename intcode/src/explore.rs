@@ -0,0 +1,464 @@
+use crate::computer::{Error, IntcodeComputer, IoBuffer, StopStatus};
+use std::cell::{Cell, Ref, RefCell};
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Write {
+    pub sample: usize,
+    pub address: usize,
+    pub value: i128,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Change {
+    pub address: usize,
+    pub before: i128,
+    pub after: i128,
+}
+
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub sample: usize,
+    cells: Vec<i128>,
+}
+
+impl Snapshot {
+    pub fn get(&self, address: usize) -> i128 {
+        self.cells[address]
+    }
+    pub fn diff(&self, later: &Snapshot) -> Vec<Change> {
+        self.cells
+            .iter()
+            .zip(later.cells.iter())
+            .enumerate()
+            .filter(|(_, (before, after))| before != after)
+            .map(|(address, (&before, &after))| Change {
+                address,
+                before,
+                after,
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Correlation {
+    pub signal: String,
+    pub address: usize,
+    pub offset: i128,
+}
+
+impl fmt::Display for Correlation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}] = {}", self.address, self.signal)?;
+        if self.offset != 0 {
+            write!(f, " {:+}", self.offset)?;
+        }
+        Ok(())
+    }
+}
+
+struct Signal {
+    name: String,
+    first_value: i128,
+    varied: bool,
+    candidates: Vec<(usize, i128)>,
+}
+
+#[derive(Default)]
+struct Correlator {
+    signals: Vec<Signal>,
+}
+
+impl Correlator {
+    fn observe(&mut self, name: &str, value: i128, cells: &[i128]) {
+        if let Some(signal) = self.signals.iter_mut().find(|s| s.name == name) {
+            signal.varied |= value != signal.first_value;
+            signal
+                .candidates
+                .retain(|&(address, offset)| cells[address] - value == offset);
+        } else {
+            self.signals.push(Signal {
+                name: name.to_string(),
+                first_value: value,
+                varied: false,
+                candidates: cells
+                    .iter()
+                    .enumerate()
+                    .map(|(address, &cell)| (address, cell - value))
+                    .collect(),
+            });
+        }
+    }
+    fn correlations(&self) -> Vec<Correlation> {
+        let mut correlations = Vec::new();
+        for signal in self.signals.iter().filter(|s| s.varied) {
+            let exact = signal.candidates.iter().any(|&(_, offset)| offset == 0);
+            for &(address, offset) in &signal.candidates {
+                if !exact || offset == 0 {
+                    correlations.push(Correlation {
+                        signal: signal.name.clone(),
+                        address,
+                        offset,
+                    });
+                }
+            }
+        }
+        correlations
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionKind {
+    Code,
+    Variable { writes: usize },
+    Data,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapEntry {
+    pub range: Range<usize>,
+    pub kind: RegionKind,
+    pub labels: Vec<Correlation>,
+}
+
+pub struct MemoryMap {
+    pub entries: Vec<MapEntry>,
+}
+
+impl fmt::Display for MemoryMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for entry in &self.entries {
+            let range = if entry.range.len() == 1 {
+                format!("{}", entry.range.start)
+            } else {
+                format!("{}..{}", entry.range.start, entry.range.end)
+            };
+            match entry.kind {
+                RegionKind::Code => write!(f, "{:>12} code", range)?,
+                RegionKind::Data => write!(f, "{:>12} data", range)?,
+                RegionKind::Variable { writes } => {
+                    write!(f, "{:>12} variable ({} writes)", range, writes)?
+                }
+            }
+            for (i, label) in entry.labels.iter().enumerate() {
+                let sep = if i == 0 { ": " } else { ", " };
+                write!(f, "{}{}", sep, label.signal)?;
+                if label.offset != 0 {
+                    write!(f, " {:+}", label.offset)?;
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct Explorer {
+    pub computer: IntcodeComputer,
+    pub input_buffer: IoBuffer,
+    pub output_buffer: IoBuffer,
+    program_len: usize,
+    writes: Rc<RefCell<Vec<Write>>>,
+    sample: Rc<Cell<usize>>,
+    executed: Vec<bool>,
+    correlator: Correlator,
+}
+
+impl Explorer {
+    pub fn new(program: &[i128]) -> Self {
+        let mut computer = IntcodeComputer::new(program);
+        let writes = Rc::new(RefCell::new(Vec::new()));
+        let sample = Rc::new(Cell::new(0));
+        let memory_len = computer.memory.len();
+        let log = writes.clone();
+        let current_sample = sample.clone();
+        computer.memory.on_write(0..memory_len, move |address, value| {
+            log.borrow_mut().push(Write {
+                sample: current_sample.get(),
+                address,
+                value,
+            });
+            value
+        });
+        Self {
+            computer,
+            input_buffer: IoBuffer::default(),
+            output_buffer: IoBuffer::default(),
+            program_len: program.len(),
+            writes,
+            sample,
+            executed: vec![false; program.len()],
+            correlator: Correlator::default(),
+        }
+    }
+    pub fn run(&mut self) -> Result<StopStatus, Error> {
+        loop {
+            let ip = self.computer.state.ip;
+            if let Some(executed) = self.executed.get_mut(ip) {
+                *executed = true;
+            }
+            if let Some(stop_status) = self
+                .computer
+                .step(&mut self.input_buffer, &mut self.output_buffer)?
+            {
+                return Ok(stop_status);
+            }
+        }
+    }
+    pub fn sample(&mut self, signals: &[(&str, i128)]) {
+        let cells = &self.computer.memory[0..self.program_len];
+        for &(name, value) in signals {
+            self.correlator.observe(name, value, cells);
+        }
+        self.sample.set(self.sample.get() + 1);
+    }
+    pub fn samples(&self) -> usize {
+        self.sample.get()
+    }
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            sample: self.sample.get(),
+            cells: self.computer.memory[0..self.program_len].to_vec(),
+        }
+    }
+    pub fn writes(&self) -> Ref<'_, [Write]> {
+        Ref::map(self.writes.borrow(), |writes| writes.as_slice())
+    }
+    pub fn correlations(&self) -> Vec<Correlation> {
+        self.correlator.correlations()
+    }
+    pub fn memory_map(&self) -> MemoryMap {
+        let mut write_counts = vec![0; self.program_len];
+        for write in self.writes.borrow().iter() {
+            if let Some(count) = write_counts.get_mut(write.address) {
+                *count += 1;
+            }
+        }
+        let mut is_code = vec![false; self.program_len];
+        for (ip, _) in self.executed.iter().enumerate().filter(|(_, &e)| e) {
+            let program = &self.computer.memory[..];
+            let size = crate::disasm::decode(
+                &program[ip..],
+                ip,
+                self.computer.extensions(),
+            )
+            .size();
+            for cell in is_code.iter_mut().skip(ip).take(size) {
+                *cell = true;
+            }
+        }
+        let correlations = self.correlations();
+        let mut labels: HashMap<usize, Vec<Correlation>> = HashMap::new();
+        for correlation in correlations {
+            labels
+                .entry(correlation.address)
+                .or_default()
+                .push(correlation);
+        }
+        let mut entries: Vec<MapEntry> = Vec::new();
+        for address in 0..self.program_len {
+            let kind = if write_counts[address] > 0 {
+                RegionKind::Variable {
+                    writes: write_counts[address],
+                }
+            } else if is_code[address] {
+                RegionKind::Code
+            } else {
+                RegionKind::Data
+            };
+            let cell_labels = labels.remove(&address).unwrap_or_default();
+            if let Some(last) = entries.last_mut() {
+                let mergeable = last.labels.is_empty() && cell_labels.is_empty();
+                let merged_kind = match (last.kind, kind) {
+                    (RegionKind::Code, RegionKind::Code) => Some(RegionKind::Code),
+                    (RegionKind::Data, RegionKind::Data) => Some(RegionKind::Data),
+                    (RegionKind::Variable { writes: a }, RegionKind::Variable { writes: b }) => {
+                        Some(RegionKind::Variable { writes: a + b })
+                    }
+                    _ => None,
+                };
+                if let (true, Some(merged_kind)) = (mergeable, merged_kind) {
+                    last.range.end = address + 1;
+                    last.kind = merged_kind;
+                    continue;
+                }
+            }
+            entries.push(MapEntry {
+                range: address..(address + 1),
+                kind,
+                labels: cell_labels,
+            });
+        }
+        MemoryMap { entries }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Tile {
+    Empty,
+    Block,
+    HorizontalPaddle,
+    Ball,
+}
+
+pub fn explore_arcade(program: &[i128], max_samples: usize) -> Result<Explorer, Error> {
+    let mut explorer = Explorer::new(program);
+    explorer.computer.memory[0] = 2;
+    let mut tiles = HashMap::new();
+    let mut ball = (0, 0);
+    let mut paddle_x = 0;
+    let mut score = 0;
+    while explorer.samples() < max_samples {
+        match explorer.run()? {
+            StopStatus::Halt => break,
+            StopStatus::WroteOutput => {
+                if explorer.output_buffer.len() == 3 {
+                    let x = explorer.output_buffer.read().unwrap();
+                    let y = explorer.output_buffer.read().unwrap();
+                    let value = explorer.output_buffer.read().unwrap();
+                    if (x, y) == (-1, 0) {
+                        score = value;
+                        continue;
+                    }
+                    let tile = match value {
+                        2 => Tile::Block,
+                        3 => Tile::HorizontalPaddle,
+                        4 => Tile::Ball,
+                        _ => Tile::Empty,
+                    };
+                    match tile {
+                        Tile::Ball => ball = (x, y),
+                        Tile::HorizontalPaddle => paddle_x = x,
+                        _ => (),
+                    }
+                    tiles.insert((x, y), tile);
+                }
+            }
+            StopStatus::WaitForInput => {
+                let blocks = tiles.values().filter(|&&t| t == Tile::Block).count() as i128;
+                explorer.sample(&[
+                    ("ball x", ball.0),
+                    ("ball y", ball.1),
+                    ("paddle x", paddle_x),
+                    ("score", score),
+                    ("blocks remaining", blocks),
+                ]);
+                explorer.input_buffer.write((ball.0 - paddle_x).signum());
+            }
+        }
+    }
+    Ok(explorer)
+}
+
+pub fn explore_droid(program: &[i128], max_samples: usize, seed: u64) -> Result<Explorer, Error> {
+    let mut explorer = Explorer::new(program);
+    let mut position = (0, 0);
+    let mut rng = seed;
+    let mut direction = 1;
+    while explorer.samples() < max_samples {
+        match explorer.run()? {
+            StopStatus::Halt => break,
+            StopStatus::WroteOutput => {
+                let status = explorer.output_buffer.read().unwrap();
+                if status != 0 {
+                    position = match direction {
+                        1 => (position.0, position.1 - 1),
+                        2 => (position.0, position.1 + 1),
+                        3 => (position.0 - 1, position.1),
+                        _ => (position.0 + 1, position.1),
+                    };
+                }
+            }
+            StopStatus::WaitForInput => {
+                explorer.sample(&[("droid x", position.0), ("droid y", position.1)]);
+                rng = rng
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                direction = (rng >> 33) as i128 % 4 + 1;
+                explorer.input_buffer.write(direction);
+            }
+        }
+    }
+    Ok(explorer)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn finds_counter_cell() {
+        let program = [3, 13, 1001, 14, 1, 14, 4, 14, 1105, 1, 0, 99, 0, 0, 0];
+        let mut explorer = Explorer::new(&program);
+        let before = explorer.snapshot();
+        let mut count = 0;
+        while explorer.samples() < 5 {
+            match explorer.run().unwrap() {
+                StopStatus::WaitForInput => {
+                    explorer.sample(&[("count", count)]);
+                    explorer.input_buffer.write(7);
+                }
+                StopStatus::WroteOutput => count = explorer.output_buffer.read().unwrap(),
+                StopStatus::Halt => panic!("unexpected halt"),
+            }
+        }
+        assert_eq!(
+            explorer.correlations(),
+            vec![Correlation {
+                signal: "count".to_string(),
+                address: 14,
+                offset: 0,
+            }]
+        );
+        assert_eq!(
+            before.diff(&explorer.snapshot()),
+            vec![
+                Change {
+                    address: 13,
+                    before: 0,
+                    after: 7,
+                },
+                Change {
+                    address: 14,
+                    before: 0,
+                    after: 4,
+                },
+            ]
+        );
+        assert_eq!(explorer.writes().len(), 8);
+        assert_eq!(
+            explorer.memory_map().to_string(),
+            "       0..11 code\n      11..13 data\n          13 variable (4 writes)\n          14 variable (4 writes): count\n"
+        );
+    }
+
+    #[test]
+    fn labels_the_ball_x_cell() {
+        // draws the ball one column further right after each joystick input, keeping its x in
+        // cell 20
+        let program = [
+            2, 20, 20, 21, 1001, 20, 1, 20, 4, 20, 104, 0, 104, 4, 3, 22, 1105, 1, 4, 99, 0, 0, 0,
+        ];
+        let explorer = explore_arcade(&program, 5).unwrap();
+        let ball_x = Correlation {
+            signal: "ball x".to_string(),
+            address: 20,
+            offset: 0,
+        };
+        assert_eq!(explorer.correlations(), vec![ball_x.clone()]);
+        let map = explorer.memory_map();
+        assert!(map.entries.contains(&MapEntry {
+            range: 20..21,
+            kind: RegionKind::Variable { writes: 5 },
+            labels: vec![ball_x],
+        }));
+        assert_eq!(
+            map.to_string(),
+            "       0..19 code\n          19 data\n          20 variable (5 writes): ball x\n      21..23 variable (5 writes)\n"
+        );
+    }
+}
@@ -0,0 +1,6 @@
+pub mod computer;
+pub mod diff;
+pub mod disasm;
+pub mod explore;
+pub mod optimise;
+mod variants;
@@ -0,0 +1,666 @@
+use crate::computer::{self, Extensions, IntcodeComputer, IoBuffer, Opcode, StopStatus};
+use crate::disasm::{self, Line, Operand};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt;
+use std::ops::Range;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RewriteKind {
+    Fold,
+    Move,
+    ConstantJump,
+    SkipNoOps,
+    ThreadJump,
+    DeadCode,
+}
+
+impl fmt::Display for RewriteKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Fold => "fold",
+            Self::Move => "move",
+            Self::ConstantJump => "constant jump",
+            Self::SkipNoOps => "skip no-ops",
+            Self::ThreadJump => "thread jump",
+            Self::DeadCode => "dead code",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rewrite {
+    pub kind: RewriteKind,
+    pub address: usize,
+    pub before: Vec<i128>,
+    pub after: Vec<i128>,
+}
+
+impl fmt::Display for Rewrite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.kind == RewriteKind::DeadCode {
+            return write!(
+                f,
+                "{}: {}..{}",
+                self.kind,
+                self.address,
+                self.address + self.before.len()
+            );
+        }
+        let extensions = Extensions::default();
+        write!(
+            f,
+            "{}: {} => {}",
+            self.kind,
+            disasm::decode(&self.before, self.address, &extensions),
+            disasm::decode(&self.after, self.address, &extensions)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionReport {
+    pub outputs: usize,
+    pub original_steps: usize,
+    pub optimised_steps: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Optimised {
+    pub program: Vec<i128>,
+    pub rewrites: Vec<Rewrite>,
+    pub sessions: Vec<SessionReport>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotEquivalent {
+    // Without a run to compare there's no way to tell if the program modifies itself
+    NoSessions,
+    Mismatch { session: usize, detail: String },
+}
+
+impl fmt::Display for NotEquivalent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NoSessions => write!(f, "no sessions to check equivalence on"),
+            Self::Mismatch { session, detail } => write!(f, "session {}: {}", session, detail),
+        }
+    }
+}
+
+impl std::error::Error for NotEquivalent {}
+
+#[derive(Debug, Clone)]
+struct Instr {
+    address: usize,
+    opcode: Opcode,
+    operands: Vec<Operand>,
+}
+
+impl Instr {
+    fn decode(words: &[i128], address: usize) -> Option<Self> {
+        let extensions = Extensions::default();
+        match disasm::decode(words.get(address..)?, address, &extensions) {
+            Line::Instruction { operands, .. } => Some(Self {
+                address,
+                opcode: Opcode::from_i128(words[address] % 100, &extensions)?,
+                operands,
+            }),
+            Line::Data { .. } => None,
+        }
+    }
+    fn next(&self) -> usize {
+        self.address + 1 + self.operands.len()
+    }
+    fn cells(&self) -> Range<usize> {
+        self.address..self.next()
+    }
+    fn unconditional_target(&self) -> Option<usize> {
+        if self.opcode != Opcode::JumpIfTrue && self.opcode != Opcode::JumpIfFalse {
+            return None;
+        }
+        match (self.opcode, self.operands[0], self.operands[1]) {
+            (Opcode::JumpIfTrue, Operand::Immediate(c), Operand::Immediate(t))
+                if c != 0 && t >= 0 =>
+            {
+                Some(t as usize)
+            }
+            (Opcode::JumpIfFalse, Operand::Immediate(0), Operand::Immediate(t)) if t >= 0 => {
+                Some(t as usize)
+            }
+            _ => None,
+        }
+    }
+}
+
+fn encode(opcode: i128, operands: &[Operand]) -> Vec<i128> {
+    let mut words = vec![opcode];
+    for (n, operand) in operands.iter().enumerate() {
+        let (mode, value) = match *operand {
+            Operand::Positional(value) => (0, value),
+            Operand::Immediate(value) => (1, value),
+            Operand::Relative(value) => (2, value),
+        };
+        words[0] += mode * 10_i128.pow(n as u32 + 2);
+        words.push(value);
+    }
+    words
+}
+
+enum Simplified {
+    Unchanged,
+    NoOp,
+    Rewrite(RewriteKind, Vec<i128>),
+}
+
+fn simplify_move(instr: &Instr, kind: RewriteKind, src: Operand, dst: Operand) -> Simplified {
+    if src == dst {
+        return Simplified::NoOp;
+    }
+    let words = encode(1, &[src, Operand::Immediate(0), dst]);
+    if words == encode(1, &instr.operands) && instr.opcode == Opcode::Add {
+        Simplified::Unchanged
+    } else {
+        Simplified::Rewrite(kind, words)
+    }
+}
+
+fn simplify(instr: &Instr) -> Simplified {
+    use Operand::Immediate;
+    match instr.opcode {
+        Opcode::Add | Opcode::Multiply | Opcode::LessThan | Opcode::Equals => {
+            let (lhs, rhs, dst) = (instr.operands[0], instr.operands[1], instr.operands[2]);
+            let folded = match (instr.opcode, lhs, rhs) {
+                (Opcode::Add, Immediate(a), Immediate(b)) => a.checked_add(b),
+                (Opcode::Multiply, Immediate(a), Immediate(b)) => a.checked_mul(b),
+                (Opcode::LessThan, Immediate(a), Immediate(b)) => Some((a < b) as i128),
+                (Opcode::Equals, Immediate(a), Immediate(b)) => Some((a == b) as i128),
+                (Opcode::LessThan, _, _) if lhs == rhs => Some(0),
+                (Opcode::Equals, _, _) if lhs == rhs => Some(1),
+                (Opcode::Multiply, Immediate(0), _) | (Opcode::Multiply, _, Immediate(0)) => {
+                    Some(0)
+                }
+                _ => None,
+            };
+            if let Some(value) = folded {
+                return simplify_move(instr, RewriteKind::Fold, Immediate(value), dst);
+            }
+            match (instr.opcode, lhs, rhs) {
+                (Opcode::Add, src, Immediate(0))
+                | (Opcode::Add, Immediate(0), src)
+                | (Opcode::Multiply, src, Immediate(1))
+                | (Opcode::Multiply, Immediate(1), src) => {
+                    simplify_move(instr, RewriteKind::Move, src, dst)
+                }
+                _ => Simplified::Unchanged,
+            }
+        }
+        Opcode::JumpIfTrue | Opcode::JumpIfFalse => {
+            let (condition, target) = (instr.operands[0], instr.operands[1]);
+            if target == Immediate(instr.next() as i128) {
+                return Simplified::NoOp;
+            }
+            match condition {
+                Immediate(c) if (c != 0) == (instr.opcode == Opcode::JumpIfTrue) => {
+                    let words = encode(5, &[Immediate(1), target]);
+                    if words == encode(5, &instr.operands) && instr.opcode == Opcode::JumpIfTrue {
+                        Simplified::Unchanged
+                    } else {
+                        Simplified::Rewrite(RewriteKind::ConstantJump, words)
+                    }
+                }
+                Immediate(_) => Simplified::NoOp,
+                _ => Simplified::Unchanged,
+            }
+        }
+        _ => Simplified::Unchanged,
+    }
+}
+
+#[derive(Default)]
+struct Analysis {
+    instructions: BTreeMap<usize, Instr>,
+    overlapping: BTreeSet<usize>,
+    data_refs: BTreeSet<usize>,
+    indirect_jumps: bool,
+    uses_relative: bool,
+}
+
+impl Analysis {
+    fn new(words: &[i128], entries: &BTreeSet<usize>) -> Self {
+        let mut analysis = Self::default();
+        let mut pending = vec![0];
+        pending.extend(entries.iter().cloned());
+        while let Some(address) = pending.pop() {
+            if analysis.instructions.contains_key(&address) {
+                continue;
+            }
+            let instr = match Instr::decode(words, address) {
+                Some(instr) => instr,
+                None => continue,
+            };
+            for operand in &instr.operands {
+                match *operand {
+                    Operand::Positional(address) if address >= 0 => {
+                        analysis.data_refs.insert(address as usize);
+                    }
+                    Operand::Relative(_) => analysis.uses_relative = true,
+                    _ => (),
+                }
+            }
+            match instr.opcode {
+                Opcode::Halt => (),
+                Opcode::JumpIfTrue | Opcode::JumpIfFalse => {
+                    let (taken, not_taken) = match instr.operands[0] {
+                        Operand::Immediate(c) => {
+                            let taken = (c != 0) == (instr.opcode == Opcode::JumpIfTrue);
+                            (taken, !taken)
+                        }
+                        _ => (true, true),
+                    };
+                    if taken {
+                        match instr.operands[1] {
+                            Operand::Immediate(target) if target >= 0 => {
+                                pending.push(target as usize)
+                            }
+                            Operand::Immediate(_) => (),
+                            _ => analysis.indirect_jumps = true,
+                        }
+                    }
+                    if not_taken {
+                        pending.push(instr.next());
+                    }
+                }
+                _ => pending.push(instr.next()),
+            }
+            analysis.instructions.insert(address, instr);
+        }
+        let mut end = 0;
+        let mut previous = None;
+        for instr in analysis.instructions.values() {
+            if instr.address < end {
+                analysis.overlapping.extend(previous);
+                analysis.overlapping.insert(instr.address);
+            }
+            if instr.next() > end {
+                end = instr.next();
+                previous = Some(instr.address);
+            }
+        }
+        analysis
+    }
+    fn code_cells(&self) -> BTreeSet<usize> {
+        self.instructions
+            .values()
+            .flat_map(|instr| instr.cells())
+            .collect()
+    }
+}
+
+struct Plan {
+    program: Vec<i128>,
+    rewrites: Vec<Rewrite>,
+    watched: BTreeSet<usize>,
+    changed: BTreeSet<usize>,
+}
+
+impl Plan {
+    fn apply(&mut self, kind: RewriteKind, address: usize, after: Vec<i128>, span: Range<usize>) {
+        let cells = address..address + after.len();
+        let before = self.program[cells.clone()].to_vec();
+        self.program[cells.clone()].copy_from_slice(&after);
+        self.changed.extend(cells);
+        self.watched.extend(span);
+        self.rewrites.push(Rewrite {
+            kind,
+            address,
+            before,
+            after,
+        });
+    }
+
+    fn new(program: &[i128], executed: &BTreeSet<usize>, volatile: &BTreeSet<usize>) -> Self {
+        let analysis = Analysis::new(program, executed);
+        let mut volatile = volatile.clone();
+        volatile.extend(analysis.data_refs.iter().cloned());
+        for address in &analysis.overlapping {
+            volatile.extend(analysis.instructions[address].cells());
+        }
+        let stable = |cells: Range<usize>| cells.clone().all(|cell| !volatile.contains(&cell));
+        let mut plan = Self {
+            program: program.to_vec(),
+            rewrites: Vec::new(),
+            watched: BTreeSet::new(),
+            changed: BTreeSet::new(),
+        };
+
+        let mut no_ops = BTreeSet::new();
+        for instr in analysis.instructions.values() {
+            if !stable(instr.cells()) {
+                continue;
+            }
+            match simplify(instr) {
+                Simplified::Unchanged => (),
+                Simplified::NoOp => {
+                    no_ops.insert(instr.address);
+                }
+                Simplified::Rewrite(kind, words) => {
+                    plan.apply(kind, instr.address, words, instr.cells())
+                }
+            }
+        }
+
+        let mut skipped_to = 0;
+        for &start in &no_ops {
+            if start < skipped_to {
+                continue;
+            }
+            let mut end = start;
+            while no_ops.contains(&end) {
+                end = analysis.instructions[&end].next();
+            }
+            skipped_to = end;
+            let first = &analysis.instructions[&start];
+            if first.next() == end || first.operands.len() < 2 {
+                continue;
+            }
+            let words = encode(5, &[Operand::Immediate(1), Operand::Immediate(end as i128)]);
+            plan.apply(RewriteKind::SkipNoOps, start, words, start..end);
+        }
+
+        for &address in analysis.instructions.keys() {
+            let jump = match Instr::decode(&plan.program, address) {
+                Some(instr) if stable(instr.cells()) => instr,
+                _ => continue,
+            };
+            let first = match (jump.opcode, jump.operands.get(1)) {
+                (Opcode::JumpIfTrue, Some(&Operand::Immediate(target)))
+                | (Opcode::JumpIfFalse, Some(&Operand::Immediate(target)))
+                    if target >= 0 =>
+                {
+                    target as usize
+                }
+                _ => continue,
+            };
+            let mut target = first;
+            let mut seen = HashSet::new();
+            seen.insert(address);
+            let mut through = Vec::new();
+            while let Some(next) = Instr::decode(&plan.program, target)
+                .filter(|instr| stable(instr.cells()))
+                .and_then(|instr| {
+                    through.push(instr.cells());
+                    instr.unconditional_target()
+                })
+            {
+                if !seen.insert(target) {
+                    break;
+                }
+                target = next;
+            }
+            if target != first {
+                let mut words = plan.program[jump.cells()].to_vec();
+                words[2] = target as i128;
+                plan.apply(RewriteKind::ThreadJump, address, words, jump.cells());
+                for cells in through {
+                    plan.watched.extend(cells);
+                }
+            }
+        }
+
+        let original = Analysis::new(program, &BTreeSet::new());
+        let code = original.code_cells();
+        let self_modifying = original.data_refs.iter().any(|cell| code.contains(cell));
+        let fully_static = executed
+            .iter()
+            .all(|ip| original.instructions.contains_key(ip));
+        if !original.indirect_jumps && !original.uses_relative && !self_modifying && fully_static {
+            let rewritten = Analysis::new(&plan.program, &BTreeSet::new());
+            let live = rewritten.code_cells();
+            let dead = |cell: &usize| {
+                !live.contains(cell)
+                    && !original.data_refs.contains(cell)
+                    && !rewritten.data_refs.contains(cell)
+                    && !volatile.contains(cell)
+            };
+            let mut address = 0;
+            while address < plan.program.len() {
+                let end = (address..plan.program.len())
+                    .find(|cell| !dead(cell))
+                    .unwrap_or(plan.program.len());
+                if plan.program[address..end].iter().any(|&word| word != 0) {
+                    plan.apply(
+                        RewriteKind::DeadCode,
+                        address,
+                        vec![0; end - address],
+                        address..end,
+                    );
+                }
+                address = end + 1;
+            }
+            while plan.program.last() == Some(&0) {
+                plan.program.pop();
+            }
+        }
+        plan
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Halt,
+    WaitForInput,
+    StepLimit,
+    Error(computer::Error),
+}
+
+struct Run {
+    outputs: Vec<i128>,
+    outcome: Outcome,
+    steps: usize,
+    memory: Vec<i128>,
+    executed: HashSet<usize>,
+    touched: BTreeSet<usize>,
+}
+
+fn ranges(cells: &BTreeSet<usize>) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    for &cell in cells {
+        match ranges.last_mut() {
+            Some(range) if range.end == cell => range.end += 1,
+            _ => ranges.push(cell..cell + 1),
+        }
+    }
+    ranges
+}
+
+fn run(program: &[i128], inputs: &[i128], watched: &BTreeSet<usize>, max_steps: usize) -> Run {
+    let mut computer = IntcodeComputer::new(program);
+    let touched = Rc::new(RefCell::new(BTreeSet::new()));
+    for range in ranges(watched) {
+        let read_log = touched.clone();
+        computer
+            .memory
+            .on_read(range.clone(), move |address, value| {
+                read_log.borrow_mut().insert(address);
+                value
+            });
+        let write_log = touched.clone();
+        computer.memory.on_write(range, move |address, value| {
+            write_log.borrow_mut().insert(address);
+            value
+        });
+    }
+    let mut input_buffer = IoBuffer::default();
+    for &value in inputs {
+        input_buffer.write(value);
+    }
+    let mut output_buffer = IoBuffer::default();
+    let mut executed = HashSet::new();
+    let mut steps = 0;
+    let outcome = loop {
+        if steps == max_steps {
+            break Outcome::StepLimit;
+        }
+        executed.insert(computer.state.ip);
+        steps += 1;
+        match computer.step(&mut input_buffer, &mut output_buffer) {
+            Err(error) => break Outcome::Error(error),
+            Ok(Some(StopStatus::Halt)) => break Outcome::Halt,
+            Ok(Some(StopStatus::WaitForInput)) => break Outcome::WaitForInput,
+            Ok(_) => (),
+        }
+    };
+    let touched = touched.borrow().clone();
+    Run {
+        outputs: output_buffer.drain().collect(),
+        outcome,
+        steps,
+        memory: computer.memory.to_vec(),
+        executed,
+        touched,
+    }
+}
+
+impl Run {
+    fn mismatch(&self, other: &Run, ignored: &BTreeSet<usize>) -> Option<String> {
+        if self.outputs != other.outputs {
+            let i = (0..)
+                .find(|&i| self.outputs.get(i) != other.outputs.get(i))
+                .unwrap();
+            return Some(format!(
+                "output {}: expected {:?}, got {:?}",
+                i,
+                self.outputs.get(i),
+                other.outputs.get(i)
+            ));
+        }
+        if self.outcome != other.outcome {
+            return Some(format!(
+                "expected {:?}, got {:?}",
+                self.outcome, other.outcome
+            ));
+        }
+        self.memory
+            .iter()
+            .zip(other.memory.iter())
+            .enumerate()
+            .find(|(address, (a, b))| a != b && !ignored.contains(address))
+            .map(|(address, (a, b))| format!("expected [{}] = {}, got {}", address, a, b))
+    }
+}
+
+pub fn optimise(
+    program: &[i128],
+    sessions: &[Vec<i128>],
+    max_steps: usize,
+) -> Result<Optimised, NotEquivalent> {
+    if sessions.is_empty() {
+        return Err(NotEquivalent::NoSessions);
+    }
+    let unwatched = BTreeSet::new();
+    let originals = sessions
+        .iter()
+        .map(|inputs| run(program, inputs, &unwatched, max_steps))
+        .collect::<Vec<_>>();
+    let executed = originals
+        .iter()
+        .flat_map(|run| run.executed.iter().cloned())
+        .collect::<BTreeSet<_>>();
+    let mut volatile = BTreeSet::new();
+    loop {
+        let plan = Plan::new(program, &executed, &volatile);
+        let touched = sessions
+            .iter()
+            .flat_map(|inputs| run(program, inputs, &plan.watched, max_steps).touched)
+            .collect::<BTreeSet<_>>();
+        if !touched.is_empty() {
+            volatile.extend(touched);
+            continue;
+        }
+        let mut reports = Vec::new();
+        for (session, (inputs, original)) in sessions.iter().zip(originals.iter()).enumerate() {
+            let optimised = run(&plan.program, inputs, &unwatched, max_steps);
+            if let Some(detail) = original.mismatch(&optimised, &plan.changed) {
+                return Err(NotEquivalent::Mismatch { session, detail });
+            }
+            reports.push(SessionReport {
+                outputs: original.outputs.len(),
+                original_steps: original.steps,
+                optimised_steps: optimised.steps,
+            });
+        }
+        return Ok(Optimised {
+            program: plan.program,
+            rewrites: plan.rewrites,
+            sessions: reports,
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const MAX_STEPS: usize = 10_000_000;
+
+    #[test]
+    fn folds_constants_and_removes_dead_code() {
+        let program = [
+            1101, 2, 3, 30, 1106, 0, 10, 4, 30, 99, 1105, 1, 16, 99, 0, 0, 1001, 30, 0, 31, 1002,
+            31, 1, 31, 1105, 0, 0, 4, 31, 99, 0, 0,
+        ];
+        let optimised = optimise(&program, &[vec![]], MAX_STEPS).unwrap();
+        assert_eq!(
+            optimised.program,
+            vec![
+                1101, 5, 0, 30, 1105, 1, 16, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1001, 30, 0, 31, 1105, 1,
+                27, 0, 0, 0, 0, 4, 31, 99
+            ]
+        );
+        assert_eq!(
+            optimised.sessions,
+            vec![SessionReport {
+                outputs: 1,
+                original_steps: 8,
+                optimised_steps: 6,
+            }]
+        );
+    }
+
+    #[test]
+    fn leaves_self_modified_instructions_alone() {
+        let program = [109, 7, 21101, 40, 0, 1, 1101, 2, 3, 20, 4, 20, 99];
+        let optimised = optimise(&program, &[vec![]], MAX_STEPS).unwrap();
+        assert_eq!(optimised.program, program.to_vec());
+        assert!(optimised.rewrites.is_empty());
+    }
+
+    #[test]
+    fn no_sessions_is_an_error() {
+        // the relative write turns the ADD's first operand into 7, which only a run reveals
+        let program = [109, 7, 21101, 7, 0, 0, 1101, 2, 3, 20, 4, 20, 99];
+        assert_eq!(
+            optimise(&program, &[], MAX_STEPS).unwrap_err(),
+            NotEquivalent::NoSessions
+        );
+        let optimised = optimise(&program, &[vec![0]], MAX_STEPS).unwrap();
+        assert_eq!(optimised.program, program.to_vec());
+    }
+
+    #[test]
+    fn boost_program_is_equivalent() {
+        let program = include_str!("../../day9-1/input")
+            .split(',')
+            .map(|s| s.trim().parse::<i128>().unwrap())
+            .collect::<Vec<_>>();
+        let optimised = optimise(&program, &[vec![1], vec![2]], MAX_STEPS).unwrap();
+        assert_eq!(optimised.sessions.len(), 2);
+        assert!(optimised
+            .sessions
+            .iter()
+            .all(|report| report.optimised_steps <= report.original_steps));
+    }
+}
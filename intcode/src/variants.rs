@@ -0,0 +1,361 @@
+use crate::diff::{Dialect, Variant};
+
+macro_rules! add_mul_variant {
+    ($module:ident, $path:expr, |$machine:ident| $step:expr) => {
+        #[allow(warnings, clippy::all)]
+        mod $module {
+            include!($path);
+
+            pub struct Machine {
+                memory: Vec<usize>,
+                ip: usize,
+            }
+
+            pub fn new(program: &[i128], _inputs: &[i128]) -> Box<dyn crate::diff::Machine> {
+                Box::new(Machine {
+                    memory: program.iter().map(|&value| value as usize).collect(),
+                    ip: 0,
+                })
+            }
+
+            impl crate::diff::Machine for Machine {
+                fn ip(&self) -> usize {
+                    self.ip
+                }
+                fn peek(&self, address: usize) -> Option<i128> {
+                    self.memory.get(address).map(|&value| value as i128)
+                }
+                fn memory_len(&self) -> usize {
+                    self.memory.len()
+                }
+                fn step(&mut self) -> crate::diff::Step {
+                    let $machine = self;
+                    $step
+                }
+            }
+        }
+    };
+}
+
+macro_rules! split_buffer_variant {
+    ($module:ident, $path:expr) => {
+        #[allow(warnings, clippy::all)]
+        mod $module {
+            include!($path);
+
+            pub struct Machine {
+                memory: Vec<i32>,
+                ip: usize,
+                input_buffer: InputBuffer,
+                output_buffer: OutputBuffer,
+            }
+
+            pub fn new(program: &[i128], inputs: &[i128]) -> Box<dyn crate::diff::Machine> {
+                let mut input_buffer = InputBuffer::default();
+                input_buffer
+                    .values
+                    .extend(inputs.iter().map(|&value| value as i32));
+                Box::new(Machine {
+                    memory: program.iter().map(|&value| value as i32).collect(),
+                    ip: 0,
+                    input_buffer,
+                    output_buffer: OutputBuffer::default(),
+                })
+            }
+
+            impl crate::diff::Machine for Machine {
+                fn ip(&self) -> usize {
+                    self.ip
+                }
+                fn peek(&self, address: usize) -> Option<i128> {
+                    self.memory.get(address).map(|&value| value as i128)
+                }
+                fn memory_len(&self) -> usize {
+                    self.memory.len()
+                }
+                fn step(&mut self) -> crate::diff::Step {
+                    if self.memory[self.ip] % 100 == 3 && self.input_buffer.values.is_empty() {
+                        return crate::diff::Step::WaitForInput;
+                    }
+                    let instruction = Instruction::decode(self.memory[self.ip]);
+                    match instruction.run(
+                        &mut self.memory,
+                        self.ip,
+                        &mut self.input_buffer,
+                        &mut self.output_buffer,
+                    ) {
+                        Run::Halt => crate::diff::Step::Halt,
+                        Run::SetIp(ip) => {
+                            self.ip = ip;
+                            match self.output_buffer.values.pop_front() {
+                                Some(value) => crate::diff::Step::Output(value as i128),
+                                None => crate::diff::Step::Running,
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    };
+}
+
+macro_rules! io_buffer_i32_variant {
+    ($module:ident, $path:expr) => {
+        #[allow(warnings, clippy::all)]
+        mod $module {
+            include!($path);
+
+            pub struct Machine {
+                memory: Vec<i32>,
+                ip: usize,
+                input_buffer: IoBuffer,
+                output_buffer: IoBuffer,
+            }
+
+            pub fn new(program: &[i128], inputs: &[i128]) -> Box<dyn crate::diff::Machine> {
+                let mut input_buffer = IoBuffer::default();
+                for &value in inputs {
+                    input_buffer.write(value as i32);
+                }
+                Box::new(Machine {
+                    memory: program.iter().map(|&value| value as i32).collect(),
+                    ip: 0,
+                    input_buffer,
+                    output_buffer: IoBuffer::default(),
+                })
+            }
+
+            impl crate::diff::Machine for Machine {
+                fn ip(&self) -> usize {
+                    self.ip
+                }
+                fn peek(&self, address: usize) -> Option<i128> {
+                    self.memory.get(address).map(|&value| value as i128)
+                }
+                fn memory_len(&self) -> usize {
+                    self.memory.len()
+                }
+                fn step(&mut self) -> crate::diff::Step {
+                    let instruction = Instruction::decode(self.memory[self.ip]);
+                    match instruction.step(
+                        &mut self.memory,
+                        self.ip,
+                        &mut self.input_buffer,
+                        &mut self.output_buffer,
+                    ) {
+                        Step::Halt => crate::diff::Step::Halt,
+                        Step::WaitForInput => crate::diff::Step::WaitForInput,
+                        Step::SetIp(ip) => {
+                            self.ip = ip;
+                            match self.output_buffer.read() {
+                                Some(value) => crate::diff::Step::Output(value as i128),
+                                None => crate::diff::Step::Running,
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    };
+}
+
+macro_rules! relative_variant {
+    ($module:ident, $path:expr) => {
+        #[allow(warnings, clippy::all)]
+        mod $module {
+            include!($path);
+
+            pub struct Machine {
+                computer: IntcodeComputer,
+                input_buffer: IoBuffer,
+                output_buffer: IoBuffer,
+            }
+
+            pub fn new(program: &[i128], inputs: &[i128]) -> Box<dyn crate::diff::Machine> {
+                let mut input_buffer = IoBuffer::default();
+                for &value in inputs {
+                    input_buffer.write(value);
+                }
+                Box::new(Machine {
+                    computer: IntcodeComputer::new(program),
+                    input_buffer,
+                    output_buffer: IoBuffer::default(),
+                })
+            }
+
+            impl crate::diff::Machine for Machine {
+                fn ip(&self) -> usize {
+                    self.computer.state.ip
+                }
+                fn relative_base(&self) -> i128 {
+                    self.computer.state.relative_base
+                }
+                fn peek(&self, address: usize) -> Option<i128> {
+                    self.computer.memory.get(address).cloned()
+                }
+                fn memory_len(&self) -> usize {
+                    self.computer.memory.len()
+                }
+                fn step(&mut self) -> crate::diff::Step {
+                    let computer = &mut self.computer;
+                    let instruction = Instruction::decode(computer.memory[computer.state.ip]);
+                    match instruction.run(
+                        &mut computer.memory,
+                        &mut computer.state,
+                        &mut self.input_buffer,
+                        &mut self.output_buffer,
+                    ) {
+                        Status::Halt => crate::diff::Step::Halt,
+                        Status::WaitForInput => crate::diff::Step::WaitForInput,
+                        _ => match self.output_buffer.read() {
+                            Some(value) => crate::diff::Step::Output(value),
+                            None => crate::diff::Step::Running,
+                        },
+                    }
+                }
+            }
+        }
+    };
+}
+
+add_mul_variant!(day2_1, "../../day2-1/src/main.rs", |machine| {
+    let pc = machine.ip;
+    match machine.memory[pc] {
+        opcode::ADD => add(pc, &mut machine.memory),
+        opcode::MUL => mul(pc, &mut machine.memory),
+        opcode::END => return crate::diff::Step::Halt,
+        _ => panic!(),
+    }
+    machine.ip += 4;
+    crate::diff::Step::Running
+});
+add_mul_variant!(day2_2, "../../day2-2/src/main.rs", |machine| {
+    let mut program = Program {
+        ip: machine.ip,
+        memory: std::mem::take(&mut machine.memory),
+    };
+    let step = match program.memory[program.ip] {
+        opcode::ADD => {
+            program.add();
+            crate::diff::Step::Running
+        }
+        opcode::MUL => {
+            program.mul();
+            crate::diff::Step::Running
+        }
+        opcode::END => crate::diff::Step::Halt,
+        _ => panic!(),
+    };
+    machine.ip = program.ip;
+    machine.memory = program.memory;
+    step
+});
+split_buffer_variant!(day5_1, "../../day5-1/src/main.rs");
+split_buffer_variant!(day5_2, "../../day5-2/src/main.rs");
+split_buffer_variant!(day7_1, "../../day7-1/src/main.rs");
+io_buffer_i32_variant!(day7_2, "../../day7-2/src/main.rs");
+relative_variant!(day9_1, "../../day9-1/src/main.rs");
+relative_variant!(day9_2, "../../day9-2/src/main.rs");
+relative_variant!(day11_1, "../../day11-1/src/main.rs");
+relative_variant!(day11_2, "../../day11-2/src/main.rs");
+relative_variant!(day13_1, "../../day13-1/src/main.rs");
+relative_variant!(day13_2, "../../day13-2/src/main.rs");
+relative_variant!(day15_1, "../../day15-1/src/main.rs");
+relative_variant!(day15_2, "../../day15-2/src/main.rs");
+relative_variant!(day17_1, "../../day17-1/src/main.rs");
+relative_variant!(day17_2, "../../day17-2/src/main.rs");
+relative_variant!(day19_1, "../../day19-1/src/main.rs");
+relative_variant!(day19_2, "../../day19-2/src/main.rs");
+relative_variant!(day21_1, "../../day21-1/src/main.rs");
+relative_variant!(day21_2, "../../day21-2/src/main.rs");
+relative_variant!(day23_1, "../../day23-1/src/main.rs");
+relative_variant!(day23_2, "../../day23-2/src/main.rs");
+relative_variant!(day25_1, "../../day25-1/src/main.rs");
+
+mod library {
+    use crate::computer::{IntcodeComputer, IoBuffer, StopStatus};
+
+    pub struct Machine {
+        computer: IntcodeComputer,
+        input_buffer: IoBuffer,
+        output_buffer: IoBuffer,
+    }
+
+    pub fn new(program: &[i128], inputs: &[i128]) -> Box<dyn crate::diff::Machine> {
+        let mut input_buffer = IoBuffer::default();
+        for &value in inputs {
+            input_buffer.write(value);
+        }
+        Box::new(Machine {
+            computer: IntcodeComputer::new(program),
+            input_buffer,
+            output_buffer: IoBuffer::default(),
+        })
+    }
+
+    impl crate::diff::Machine for Machine {
+        fn ip(&self) -> usize {
+            self.computer.state.ip
+        }
+        fn relative_base(&self) -> i128 {
+            self.computer.state.relative_base
+        }
+        fn peek(&self, address: usize) -> Option<i128> {
+            self.computer.memory.get(address).cloned()
+        }
+        fn memory_len(&self) -> usize {
+            self.computer.memory.len()
+        }
+        fn step(&mut self) -> crate::diff::Step {
+            match self
+                .computer
+                .step(&mut self.input_buffer, &mut self.output_buffer)
+            {
+                Err(error) => panic!("{}", error),
+                Ok(Some(StopStatus::Halt)) => crate::diff::Step::Halt,
+                Ok(Some(StopStatus::WaitForInput)) => crate::diff::Step::WaitForInput,
+                Ok(_) => match self.output_buffer.read() {
+                    Some(value) => crate::diff::Step::Output(value),
+                    None => crate::diff::Step::Running,
+                },
+            }
+        }
+    }
+}
+
+macro_rules! variant {
+    ($name:expr, $module:ident, $dialect:expr) => {
+        Variant {
+            name: $name,
+            dialect: $dialect,
+            new: $module::new,
+        }
+    };
+}
+
+pub static ALL: &[Variant] = &[
+    variant!("day2-1", day2_1, Dialect::ADD_MUL),
+    variant!("day2-2", day2_2, Dialect::ADD_MUL),
+    variant!("day5-1", day5_1, Dialect::IO),
+    variant!("day5-2", day5_2, Dialect::JUMPS),
+    variant!("day7-1", day7_1, Dialect::JUMPS),
+    variant!("day7-2", day7_2, Dialect::JUMPS),
+    variant!("day9-1", day9_1, Dialect::RELATIVE),
+    variant!("day9-2", day9_2, Dialect::RELATIVE),
+    variant!("day11-1", day11_1, Dialect::RELATIVE),
+    variant!("day11-2", day11_2, Dialect::RELATIVE),
+    variant!("day13-1", day13_1, Dialect::RELATIVE),
+    variant!("day13-2", day13_2, Dialect::RELATIVE),
+    variant!("day15-1", day15_1, Dialect::RELATIVE),
+    variant!("day15-2", day15_2, Dialect::RELATIVE),
+    variant!("day17-1", day17_1, Dialect::RELATIVE),
+    variant!("day17-2", day17_2, Dialect::RELATIVE),
+    variant!("day19-1", day19_1, Dialect::RELATIVE),
+    variant!("day19-2", day19_2, Dialect::RELATIVE),
+    variant!("day21-1", day21_1, Dialect::RELATIVE),
+    variant!("day21-2", day21_2, Dialect::RELATIVE),
+    variant!("day23-1", day23_1, Dialect::RELATIVE),
+    variant!("day23-2", day23_2, Dialect::RELATIVE),
+    variant!("intcode", library, Dialect::RELATIVE),
+    variant!("day25-1", day25_1, Dialect::RELATIVE),
+];
@@ -0,0 +1,314 @@
+const SMALL_GLYPHS: &[(char, &[&str])] = &[
+    ('A', &[".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', &["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', &[".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', &["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', &["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', &[".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', &["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', &["###", ".#.", ".#.", ".#.", ".#.", "###"]),
+    ('J', &["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', &["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', &["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', &[".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', &["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', &["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', &[".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', &["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Y', &["#...#", "#...#", ".#.#.", "..#..", "..#..", "..#.."]),
+    ('Z', &["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+const LARGE_GLYPHS: &[(char, &[&str])] = &[
+    (
+        'A',
+        &[
+            "..##..", ".#..#.", "#....#", "#....#", "#....#", "######", "#....#", "#....#",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'B',
+        &[
+            "#####.", "#....#", "#....#", "#....#", "#####.", "#....#", "#....#", "#....#",
+            "#....#", "#####.",
+        ],
+    ),
+    (
+        'C',
+        &[
+            ".####.", "#....#", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....",
+            "#....#", ".####.",
+        ],
+    ),
+    (
+        'E',
+        &[
+            "######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....",
+            "#.....", "######",
+        ],
+    ),
+    (
+        'F',
+        &[
+            "######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....",
+            "#.....", "#.....",
+        ],
+    ),
+    (
+        'G',
+        &[
+            ".####.", "#....#", "#.....", "#.....", "#.....", "#..###", "#....#", "#....#",
+            "#...##", ".###.#",
+        ],
+    ),
+    (
+        'H',
+        &[
+            "#....#", "#....#", "#....#", "#....#", "######", "#....#", "#....#", "#....#",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'J',
+        &[
+            "...###", "....#.", "....#.", "....#.", "....#.", "....#.", "....#.", "#...#.",
+            "#...#.", ".###..",
+        ],
+    ),
+    (
+        'K',
+        &[
+            "#....#", "#...#.", "#..#..", "#.#...", "##....", "##....", "#.#...", "#..#..",
+            "#...#.", "#....#",
+        ],
+    ),
+    (
+        'L',
+        &[
+            "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....",
+            "#.....", "######",
+        ],
+    ),
+    (
+        'N',
+        &[
+            "#....#", "##...#", "##...#", "#.#..#", "#.#..#", "#..#.#", "#..#.#", "#...##",
+            "#...##", "#....#",
+        ],
+    ),
+    (
+        'P',
+        &[
+            "#####.", "#....#", "#....#", "#....#", "#####.", "#.....", "#.....", "#.....",
+            "#.....", "#.....",
+        ],
+    ),
+    (
+        'R',
+        &[
+            "#####.", "#....#", "#....#", "#....#", "#####.", "#..#..", "#...#.", "#...#.",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'X',
+        &[
+            "#....#", "#....#", ".#..#.", ".#..#.", "..##..", "..##..", ".#..#.", ".#..#.",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'Z',
+        &[
+            "######", ".....#", ".....#", "....#.", "...#..", "..#...", ".#....", "#.....",
+            "#.....", "######",
+        ],
+    ),
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum OcrError {
+    // only the 6 and 10 cell tall fonts are known
+    UnsupportedHeight(usize),
+    UnknownGlyph { column: usize, pattern: String },
+}
+
+impl std::fmt::Display for OcrError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            OcrError::UnsupportedHeight(height) => {
+                write!(f, "no font is {} cells tall", height)
+            }
+            OcrError::UnknownGlyph { column, pattern } => {
+                write!(f, "unknown glyph at column {}:\n{}", column, pattern)
+            }
+        }
+    }
+}
+
+struct Image<'a> {
+    rows: &'a [Vec<bool>],
+    width: usize,
+}
+
+impl<'a> Image<'a> {
+    fn get(&self, x: usize, y: usize) -> bool {
+        self.rows[y].get(x).cloned().unwrap_or(false)
+    }
+    fn column_is_blank(&self, x: usize) -> bool {
+        (0..self.rows.len()).all(|y| !self.get(x, y))
+    }
+    fn matches(&self, x: usize, glyph: &[&str]) -> bool {
+        glyph.iter().enumerate().all(|(y, row)| {
+            row.chars()
+                .enumerate()
+                .all(|(i, ch)| self.get(x + i, y) == (ch == '#'))
+        })
+    }
+    fn pattern(&self, x: usize, width: usize) -> String {
+        let rows = (0..self.rows.len())
+            .map(|y| {
+                (x..(x + width).min(self.width))
+                    .map(|x| if self.get(x, y) { '#' } else { '.' })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+        rows.join("\n")
+    }
+}
+
+// Glyphs are stored without blank columns on either side, as letters can be narrower than
+// their cell (I) or fill it completely and touch the next letter (Y)
+fn trimmed_glyph(glyph: &[&'static str]) -> Vec<&'static str> {
+    let is_blank = |i: usize| glyph.iter().all(|row| row.as_bytes()[i] == b'.');
+    let width = glyph[0].len();
+    let start = (0..width).find(|&i| !is_blank(i)).unwrap_or(width);
+    let end = (0..width)
+        .rev()
+        .find(|&i| !is_blank(i))
+        .map_or(start, |i| i + 1);
+    glyph.iter().map(|row| &row[start..end]).collect()
+}
+
+pub fn recognise(rows: &[Vec<bool>]) -> Result<String, OcrError> {
+    let is_lit = |row: &&Vec<bool>| row.iter().any(|&lit| lit);
+    let top = match rows.iter().position(|row| is_lit(&row)) {
+        Some(top) => top,
+        None => return Ok(String::new()),
+    };
+    let bottom = rows.iter().rposition(|row| is_lit(&row)).unwrap();
+    let rows = &rows[top..=bottom];
+    let (glyphs, cell_width) = match rows.len() {
+        6 => (SMALL_GLYPHS, 5),
+        10 => (LARGE_GLYPHS, 8),
+        height => return Err(OcrError::UnsupportedHeight(height)),
+    };
+    let glyphs = glyphs
+        .iter()
+        .map(|&(ch, glyph)| (ch, trimmed_glyph(glyph)))
+        .collect::<Vec<_>>();
+    let image = Image {
+        rows,
+        width: rows.iter().map(Vec::len).max().unwrap_or(0),
+    };
+    let mut text = String::new();
+    let mut x = 0;
+    while x < image.width {
+        if image.column_is_blank(x) {
+            x += 1;
+            continue;
+        }
+        let (ch, width) = glyphs
+            .iter()
+            .filter(|(_, glyph)| image.matches(x, glyph))
+            .map(|(ch, glyph)| (*ch, glyph[0].len()))
+            .max_by_key(|&(_, width)| width)
+            .ok_or_else(|| OcrError::UnknownGlyph {
+                column: x,
+                pattern: image.pattern(x, cell_width),
+            })?;
+        text.push(ch);
+        x += width;
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(s: &str) -> Vec<Vec<bool>> {
+        s.lines()
+            .map(|line| line.chars().map(|ch| ch == '#').collect())
+            .collect()
+    }
+
+    // Lays glyphs out in their cells the way the puzzles draw them
+    fn render(glyphs: &[(char, &[&str])], cell_width: usize) -> Vec<Vec<bool>> {
+        let height = glyphs[0].1.len();
+        (0..height)
+            .map(|y| {
+                let mut row = Vec::new();
+                for (_, glyph) in glyphs {
+                    let cell = format!("{:.<width$}", glyph[y], width = cell_width);
+                    row.extend(cell.chars().map(|ch| ch == '#'));
+                }
+                row
+            })
+            .collect()
+    }
+
+    fn alphabet(glyphs: &[(char, &[&str])]) -> String {
+        glyphs.iter().map(|&(ch, _)| ch).collect()
+    }
+
+    #[test]
+    fn small_font() {
+        let rows = render(SMALL_GLYPHS, 5);
+        assert_eq!(recognise(&rows), Ok(alphabet(SMALL_GLYPHS)));
+    }
+
+    #[test]
+    fn large_font() {
+        let rows = render(LARGE_GLYPHS, 8);
+        assert_eq!(recognise(&rows), Ok(alphabet(LARGE_GLYPHS)));
+    }
+
+    #[test]
+    fn touching_letters() {
+        let rows = parse(
+            "\
+.##..#...##..#.###..#...#
+#..#.#...##.#..#..#.#...#
+#.....#.#.##...###...#.#.
+#......#..#.#..#..#...#..
+#..#...#..#.#..#..#...#..
+.##....#..#..#.###....#..",
+        );
+        assert_eq!(recognise(&rows), Ok("CYKBY".to_string()));
+    }
+
+    #[test]
+    fn blank_border() {
+        let rows = parse("......\n.####.\n.#....\n.###..\n.#....\n.#....\n.####.\n......");
+        assert_eq!(recognise(&rows), Ok("E".to_string()));
+        assert_eq!(recognise(&parse("....\n....")), Ok(String::new()));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            recognise(&parse("#\n#\n#")),
+            Err(OcrError::UnsupportedHeight(3))
+        );
+        let rows = parse("####.#..#\n#..#.#..#\n#..#.#..#\n#..#.#..#\n#..#.#..#\n####.####");
+        assert_eq!(
+            recognise(&rows),
+            Err(OcrError::UnknownGlyph {
+                column: 0,
+                pattern: "####.\n#..#.\n#..#.\n#..#.\n#..#.\n####.".to_string(),
+            })
+        );
+    }
+}
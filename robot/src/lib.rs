@@ -0,0 +1,486 @@
+use std::collections::{HashMap, VecDeque};
+use std::marker::PhantomData;
+
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub struct Coord {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl std::ops::Add for Coord {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            x: self.x + rhs.x,
+            y: self.y + rhs.y,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+pub const ALL_DIRECTIONS: [Direction; 4] = [
+    Direction::North,
+    Direction::East,
+    Direction::South,
+    Direction::West,
+];
+
+impl Direction {
+    pub fn to_unit_coord(self) -> Coord {
+        match self {
+            Self::North => Coord { x: 0, y: -1 },
+            Self::East => Coord { x: 1, y: 0 },
+            Self::South => Coord { x: 0, y: 1 },
+            Self::West => Coord { x: -1, y: 0 },
+        }
+    }
+    pub fn left(self) -> Self {
+        match self {
+            Self::North => Self::West,
+            Self::East => Self::North,
+            Self::South => Self::East,
+            Self::West => Self::South,
+        }
+    }
+    pub fn right(self) -> Self {
+        match self {
+            Self::North => Self::East,
+            Self::East => Self::South,
+            Self::South => Self::West,
+            Self::West => Self::North,
+        }
+    }
+    pub fn opposite(self) -> Self {
+        self.left().left()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pose {
+    pub position: Coord,
+    pub facing: Direction,
+}
+
+/// The Intcode computer running a robot's program. Each day brings its own interpreter.
+pub trait Machine {
+    fn write_input(&mut self, value: i128);
+    /// Runs until the program writes a value, returning `None` if it halts first
+    fn read_output(&mut self) -> Option<i128>;
+}
+
+/// How a robot talks to its Intcode program: each command is sent as some inputs and answered
+/// with a status of a fixed number of outputs, which decides where the robot ends up.
+pub trait Protocol {
+    type Command;
+    type Status;
+    const STATUS_LEN: usize;
+    fn encode(command: &Self::Command) -> Vec<i128>;
+    fn decode(outputs: &[i128]) -> Self::Status;
+    fn advance(pose: Pose, command: &Self::Command, status: &Self::Status) -> Pose;
+}
+
+/// A protocol whose commands are moves, so the grid around the robot can be explored.
+pub trait Navigate: Protocol<Command = Direction> {
+    /// Whether the robot was unable to enter the cell it tried to move into
+    fn is_blocked(status: &Self::Status) -> bool;
+}
+
+pub struct Robot<M, P> {
+    machine: M,
+    pose: Pose,
+    commands: usize,
+    protocol: PhantomData<P>,
+}
+
+// Derived Clone would needlessly require the protocol to be Clone
+impl<M: Clone, P> Clone for Robot<M, P> {
+    fn clone(&self) -> Self {
+        Self {
+            machine: self.machine.clone(),
+            pose: self.pose,
+            commands: self.commands,
+            protocol: PhantomData,
+        }
+    }
+}
+
+impl<M: Machine, P: Protocol> Robot<M, P> {
+    pub fn new(machine: M, pose: Pose) -> Self {
+        Self {
+            machine,
+            pose,
+            commands: 0,
+            protocol: PhantomData,
+        }
+    }
+    pub fn pose(&self) -> Pose {
+        self.pose
+    }
+    pub fn position(&self) -> Coord {
+        self.pose.position
+    }
+    /// The number of commands answered so far
+    pub fn commands(&self) -> usize {
+        self.commands
+    }
+    /// Returns `None` if the program halts instead of answering.
+    pub fn send(&mut self, command: P::Command) -> Option<P::Status> {
+        for value in P::encode(&command) {
+            self.machine.write_input(value);
+        }
+        let mut outputs = Vec::with_capacity(P::STATUS_LEN);
+        while outputs.len() < P::STATUS_LEN {
+            outputs.push(self.machine.read_output()?);
+        }
+        let status = P::decode(&outputs);
+        self.pose = P::advance(self.pose, &command, &status);
+        self.commands += 1;
+        Some(status)
+    }
+}
+
+/// The status seen on trying to enter each cell reached by exploration
+pub struct GridMap<P: Protocol> {
+    pub start: Coord,
+    pub cells: HashMap<Coord, P::Status>,
+    /// The number of commands sent while exploring, including by any clones of the robot
+    pub commands: usize,
+}
+
+impl<P: Navigate> GridMap<P> {
+    fn new(start: Coord) -> Self {
+        Self {
+            start,
+            cells: HashMap::new(),
+            commands: 0,
+        }
+    }
+    pub fn is_known(&self, coord: Coord) -> bool {
+        coord == self.start || self.cells.contains_key(&coord)
+    }
+    pub fn is_passable(&self, coord: Coord) -> bool {
+        coord == self.start
+            || self
+                .cells
+                .get(&coord)
+                .is_some_and(|status| !P::is_blocked(status))
+    }
+    // Moves from `from` through known passable cells to the nearest cell next to an unknown one,
+    // ending with the move into the unknown cell
+    fn path_to_frontier(&self, from: Coord) -> Option<Vec<Direction>> {
+        let mut came_from = HashMap::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(from);
+        came_from.insert(from, None);
+        while let Some(coord) = queue.pop_front() {
+            for &direction in &ALL_DIRECTIONS {
+                let next_coord = coord + direction.to_unit_coord();
+                if !self.is_known(next_coord) {
+                    let mut path = vec![direction];
+                    let mut coord = coord;
+                    while let Some(&Some((previous, direction))) = came_from.get(&coord) {
+                        path.push(direction);
+                        coord = previous;
+                    }
+                    path.reverse();
+                    return Some(path);
+                }
+                if self.is_passable(next_coord) && !came_from.contains_key(&next_coord) {
+                    came_from.insert(next_coord, Some((coord, direction)));
+                    queue.push_back(next_coord);
+                }
+            }
+        }
+        None
+    }
+}
+
+// Moves through cells already known to be passable, returning `None` if the program halts
+fn follow<M: Machine, P: Navigate>(robot: &mut Robot<M, P>, path: &[Direction]) -> Option<()> {
+    for &direction in path {
+        let status = robot.send(direction)?;
+        assert!(!P::is_blocked(&status), "known cell is blocked");
+    }
+    Some(())
+}
+
+/// Repeatedly walks to the nearest unexplored cell until everything reachable is known, or the
+/// program halts.
+pub fn explore<M: Machine, P: Navigate>(robot: &mut Robot<M, P>) -> GridMap<P> {
+    fn walk<M: Machine, P: Navigate>(robot: &mut Robot<M, P>, map: &mut GridMap<P>) -> Option<()> {
+        while let Some(path) = map.path_to_frontier(robot.position()) {
+            let (&last, known) = path.split_last().unwrap();
+            follow(robot, known)?;
+            let target = robot.position() + last.to_unit_coord();
+            let status = robot.send(last)?;
+            map.cells.insert(target, status);
+        }
+        Some(())
+    }
+    let commands = robot.commands();
+    let mut map = GridMap::new(robot.position());
+    walk(robot, &mut map);
+    map.commands = robot.commands() - commands;
+    map
+}
+
+/// Depth-first, stepping back one cell once everything beyond it has been seen.
+pub fn explore_backtracking<M: Machine, P: Navigate>(robot: &mut Robot<M, P>) -> GridMap<P> {
+    fn visit<M: Machine, P: Navigate>(robot: &mut Robot<M, P>, map: &mut GridMap<P>) -> Option<()> {
+        for &direction in &ALL_DIRECTIONS {
+            let target = robot.position() + direction.to_unit_coord();
+            if map.is_known(target) {
+                continue;
+            }
+            let status = robot.send(direction)?;
+            let blocked = P::is_blocked(&status);
+            map.cells.insert(target, status);
+            if !blocked {
+                visit(robot, map)?;
+                follow(robot, &[direction.opposite()])?;
+            }
+        }
+        Some(())
+    }
+    let commands = robot.commands();
+    let mut map = GridMap::new(robot.position());
+    visit(robot, &mut map);
+    map.commands = robot.commands() - commands;
+    map
+}
+
+/// Depth-first, giving each branch its own copy of the machine so the robot never steps back.
+/// The robot passed in doesn't move.
+pub fn explore_cloning<M: Machine + Clone, P: Navigate>(robot: &Robot<M, P>) -> GridMap<P> {
+    fn visit<M: Machine + Clone, P: Navigate>(robot: &Robot<M, P>, map: &mut GridMap<P>) {
+        for &direction in &ALL_DIRECTIONS {
+            let target = robot.position() + direction.to_unit_coord();
+            if map.is_known(target) {
+                continue;
+            }
+            let mut branch = robot.clone();
+            if let Some(status) = branch.send(direction) {
+                map.commands += 1;
+                let blocked = P::is_blocked(&status);
+                map.cells.insert(target, status);
+                if !blocked {
+                    visit(&branch, map);
+                }
+            }
+        }
+    }
+    let mut map = GridMap::new(robot.position());
+    visit(robot, &mut map);
+    map
+}
+
+/// Breadth-first, walking back to the start and out again along the full path for every cell.
+/// This is how the day 15 droid originally explored, and is kept to compare against.
+pub fn explore_replaying<M: Machine, P: Navigate>(robot: &mut Robot<M, P>) -> GridMap<P> {
+    fn visit<M: Machine, P: Navigate>(robot: &mut Robot<M, P>, map: &mut GridMap<P>) -> Option<()> {
+        let mut queue = VecDeque::new();
+        queue.push_back(Vec::new());
+        while let Some(path_from_start) = queue.pop_front() {
+            follow(robot, &path_from_start)?;
+            for &direction in &ALL_DIRECTIONS {
+                let target = robot.position() + direction.to_unit_coord();
+                if map.is_known(target) {
+                    continue;
+                }
+                let status = robot.send(direction)?;
+                let blocked = P::is_blocked(&status);
+                map.cells.insert(target, status);
+                if !blocked {
+                    follow(robot, &[direction.opposite()])?;
+                    let mut path = path_from_start.clone();
+                    path.push(direction);
+                    queue.push_back(path);
+                }
+            }
+            let path_back = path_from_start
+                .iter()
+                .rev()
+                .map(|direction| direction.opposite())
+                .collect::<Vec<_>>();
+            follow(robot, &path_back)?;
+        }
+        Some(())
+    }
+    let commands = robot.commands();
+    let mut map = GridMap::new(robot.position());
+    visit(robot, &mut map);
+    map.commands = robot.commands() - commands;
+    map
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashSet;
+
+    const MAZE: &str = "\
+#######
+#.....#
+#.#.#.#
+#...#.#
+#######
+";
+
+    // A robot in `MAZE` that answers each move with 1 if it moved and 0 if it hit a wall, and
+    // halts after `limit` moves
+    #[derive(Clone)]
+    struct MazeMachine {
+        open: HashSet<Coord>,
+        position: Coord,
+        input: Option<i128>,
+        limit: usize,
+    }
+
+    impl MazeMachine {
+        fn new(limit: usize) -> Self {
+            let open = MAZE
+                .lines()
+                .enumerate()
+                .flat_map(|(y, line)| {
+                    line.chars()
+                        .enumerate()
+                        .filter(|&(_, ch)| ch == '.')
+                        .map(move |(x, _)| Coord {
+                            x: x as i32,
+                            y: y as i32,
+                        })
+                })
+                .collect();
+            Self {
+                open,
+                position: Coord { x: 1, y: 1 },
+                input: None,
+                limit,
+            }
+        }
+    }
+
+    impl Machine for MazeMachine {
+        fn write_input(&mut self, value: i128) {
+            self.input = Some(value);
+        }
+        fn read_output(&mut self) -> Option<i128> {
+            if self.limit == 0 {
+                return None;
+            }
+            self.limit -= 1;
+            let direction = ALL_DIRECTIONS[self.input.take().unwrap() as usize];
+            let next = self.position + direction.to_unit_coord();
+            if self.open.contains(&next) {
+                self.position = next;
+                Some(1)
+            } else {
+                Some(0)
+            }
+        }
+    }
+
+    struct Walker;
+
+    impl Protocol for Walker {
+        type Command = Direction;
+        type Status = bool;
+        const STATUS_LEN: usize = 1;
+        fn encode(&direction: &Direction) -> Vec<i128> {
+            vec![ALL_DIRECTIONS.iter().position(|&d| d == direction).unwrap() as i128]
+        }
+        fn decode(outputs: &[i128]) -> bool {
+            outputs[0] == 1
+        }
+        fn advance(pose: Pose, &direction: &Direction, &moved: &bool) -> Pose {
+            if moved {
+                Pose {
+                    position: pose.position + direction.to_unit_coord(),
+                    facing: direction,
+                }
+            } else {
+                pose
+            }
+        }
+    }
+
+    impl Navigate for Walker {
+        fn is_blocked(&moved: &bool) -> bool {
+            !moved
+        }
+    }
+
+    fn robot(limit: usize) -> Robot<MazeMachine, Walker> {
+        let start = Pose {
+            position: Coord { x: 1, y: 1 },
+            facing: Direction::North,
+        };
+        Robot::new(MazeMachine::new(limit), start)
+    }
+
+    // The cells as they'd appear in `MAZE`, relative to the start
+    fn render(map: &GridMap<Walker>) -> String {
+        let mut string = String::new();
+        for y in 0..5 {
+            for x in 0..7 {
+                let coord = Coord { x: x - 1, y: y - 1 } + map.start;
+                string.push(match map.cells.get(&coord) {
+                    _ if coord == map.start => '.',
+                    Some(true) => '.',
+                    Some(false) => '#',
+                    None => '?',
+                });
+            }
+            string.push('\n');
+        }
+        string
+    }
+
+    #[test]
+    fn every_explorer_finds_the_whole_maze() {
+        let mut frontier = robot(1000);
+        let mut backtracking = robot(1000);
+        let mut replaying = robot(1000);
+        let maps = [
+            explore(&mut frontier),
+            explore_backtracking(&mut backtracking),
+            explore_cloning(&robot(1000)),
+            explore_replaying(&mut replaying),
+        ];
+        // corners and the cell behind the inner wall are never tried
+        let explored = "\
+?#####?
+#.....#
+#.#.#.#
+#...#.#
+?###?#?
+";
+        for map in &maps {
+            assert_eq!(render(map), explored);
+        }
+        // the 11 open cells besides the start and 18 walls, each tried once
+        assert_eq!(maps[2].commands, 29);
+        // and a step back out of each open cell
+        assert_eq!(maps[1].commands, 29 + 11);
+        assert_eq!(maps[1].commands, backtracking.commands());
+        assert_eq!(backtracking.position(), Coord { x: 1, y: 1 });
+        assert!(maps[0].commands < maps[3].commands);
+    }
+
+    #[test]
+    fn exploration_stops_when_the_program_halts() {
+        let mut robot = robot(3);
+        let map = explore_backtracking(&mut robot);
+        assert_eq!(map.commands, 3);
+        assert_eq!(map.cells.len(), 3);
+        assert!(map.is_passable(Coord { x: 2, y: 1 }));
+        assert!(!map.is_known(Coord { x: 5, y: 1 }));
+    }
+}